@@ -1,19 +1,34 @@
 use crossterm::{
     cursor,
-    event::{read, Event, KeyCode, KeyEvent, KeyModifiers},
-    style::{Colorize, Print},
+    event::{
+        poll, read, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent,
+        KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+    },
+    style::{Attribute, Color, ContentStyle, Print},
     terminal::{self, ClearType},
-    QueueableCommand,
+    ExecutableCommand, QueueableCommand,
 };
 use std::{
-    io::{self, stdout, Write},
+    cell::RefCell,
+    convert::TryFrom,
+    env,
+    fs::File,
+    io::{self, stdout, BufWriter, Write},
+    mem,
+    ops::Range,
+    path::{Path, PathBuf},
     process,
+    time::{Duration, Instant},
 };
 use thiserror::Error;
+use treeline::{complete, match_positions, CompleteOptions, CompletionSource, Ranking, Trie};
 
 fn main() {
     let result = run();
 
+    // `run` puts the terminal in raw mode and may capture the mouse, restore the terminal before
+    // exiting, also when `run` failed
+    stdout().execute(DisableMouseCapture).ok();
     terminal::disable_raw_mode().ok();
 
     match result {
@@ -28,117 +43,3081 @@ fn main() {
 fn run() -> Result<()> {
     terminal::enable_raw_mode()?;
 
-    let mut inputs = Vec::new();
+    let mut editor = LineEditor::new();
+    if env::args().any(|arg| arg == "--exit-on-ctrl-c") {
+        editor.ctrl_c = CtrlC::Exit;
+    }
+    if let Some(prompt) =
+        env::args().find_map(|arg| arg.strip_prefix("--prompt=").map(String::from))
+    {
+        editor.prompt = prompt;
+    }
+    // Colors are given by name, like `dark_cyan`, for terminals in which the defaults are hard
+    // to read
+    if let Some(color) = env::args().find_map(|arg| color_arg(&arg, "--prompt-color=")) {
+        editor.theme.prompt = ContentStyle::new().foreground(color);
+    }
+    if let Some(color) = env::args().find_map(|arg| color_arg(&arg, "--completion-color=")) {
+        editor.theme.suggestion = ContentStyle::new().foreground(color);
+    }
+    let commands = [
+        "clear-history",
+        "exit",
+        "forget",
+        "history",
+        "password",
+        "quit",
+        "set -o emacs",
+        "set -o vi",
+    ]
+    .iter()
+    .collect::<Trie>();
+    editor.commands = Some(Box::new(commands));
+    if env::args().any(|arg| arg == "--vi") {
+        editor.set_keymap(Keymap::Vi);
+    }
+    editor.auto_pair = env::args().any(|arg| arg == "--auto-pair");
+    editor.highlight_prefix = env::args().any(|arg| arg == "--highlight-prefix");
+    // Capturing the mouse prevents selecting text in the terminal, so it's opt-in
+    if env::args().any(|arg| arg == "--mouse") {
+        stdout().execute(EnableMouseCapture)?;
+    }
+    // Exit when no line is entered within the given number of seconds
+    let timeout = env::args()
+        .find_map(|arg| arg.strip_prefix("--timeout=")?.parse().ok())
+        .map(Duration::from_secs);
+
+    // The history is kept in a file in the home directory, unless another file is given
+    let history_file = env::args()
+        .find_map(|arg| arg.strip_prefix("--history=").map(PathBuf::from))
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(HISTORY_FILE)));
+    if let Some(path) = &history_file {
+        editor.set_history(load_words(path)?);
+    }
+    // The words in the dictionary are completed like lines in the history, but aren't recalled
+    // with Up and Down. The dictionary is the first argument which isn't an option.
+    let dictionary_file = env::args()
+        .skip(1)
+        .find(|arg| !arg.starts_with("--"))
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(DICTIONARY_FILE)));
+    if let Some(path) = &dictionary_file {
+        editor.history += load_words(path)?;
+    }
+
+    let result = repl(&mut editor, timeout);
+    // Only the entered lines are saved, not the words from the dictionary
+    if let Some(path) = &history_file {
+        Trie::from_words(&editor.entries).write_to_writer(BufWriter::new(File::create(path)?))?;
+    }
+    result
+}
+
+// Reads lines and runs the commands in them until the user quits
+fn repl(editor: &mut LineEditor, timeout: Option<Duration>) -> Result<()> {
     loop {
-        let input = get_input()?;
+        let input = match timeout {
+            Some(timeout) => editor.read_line_timeout(timeout)?,
+            None => editor.read_line()?,
+        };
+        let input = match input {
+            Some(input) => input,
+            None => return Ok(()),
+        };
         let lowered_input = input.to_lowercase();
 
         if lowered_input == "q" || lowered_input == "quit" || lowered_input == "exit" {
             return Ok(());
         }
 
+        // Switch the keymap like bash does
+        if let Some(keymap) = lowered_input.strip_prefix("set -o ") {
+            match keymap.trim() {
+                "emacs" => editor.set_keymap(Keymap::Emacs),
+                "vi" => editor.set_keymap(Keymap::Vi),
+                keymap => println!("Unknown keymap '{}'", keymap),
+            }
+            continue;
+        }
+
+        if lowered_input == "password" {
+            println!("Enter a password:");
+            if let Some(password) = editor.read_password()? {
+                println!("Read a password of {} characters", password.chars().count());
+            }
+            continue;
+        }
+
+        if lowered_input == "clear-history" {
+            editor.history.clear();
+            editor.entries.clear();
+            println!("Cleared the history");
+            continue;
+        }
+
+        if let Some(line) = input.strip_prefix("forget ") {
+            if editor.forget(line) {
+                println!("Forgot '{}'", line);
+            } else {
+                println!("Nothing to forget, '{}' isn't in the history", line);
+            }
+            continue;
+        }
+
         if lowered_input == "history" {
             println!("History:");
-            for input in &inputs {
+            for input in &editor.entries {
                 println!("  {}", input);
             }
             continue;
         }
 
         println!("Storing '{}'", input);
-        inputs.push(input);
-    }
-}
-
-fn get_input() -> Result<String> {
-    print_prompt()?;
-
-    let mut line_buffer = String::new();
-    while let Event::Key(event) = read()? {
-        let mut input = None;
-        match event {
-            KeyEvent {
-                modifiers: KeyModifiers::CONTROL,
-                code: KeyCode::Char('c'),
-            } => {
-                process::exit(0);
-            }
-            KeyEvent {
-                code: KeyCode::Enter,
-                ..
-            } => {
-                break;
-            }
-            KeyEvent {
-                modifiers: KeyModifiers::CONTROL,
-                code: KeyCode::Backspace,
-            } => {
-                // TODO: After support for moving the cursor with the arrow keys is added, this
-                //       implementation will most likely fail
-                let line = line_buffer.clone();
-                let line_parts: Vec<_> = line.rsplitn(2, ' ').collect();
-                if line_parts.len() == 2 {
-                    // `line_buffer` contained multiple words
-                    line_buffer = line_parts.get(1).unwrap().to_string();
-                    let chars_to_remove = line_parts.get(0).unwrap().len() + 1;
-                    stdout()
-                        .queue(cursor::MoveLeft(chars_to_remove as u16))?
-                        .queue(terminal::Clear(ClearType::UntilNewLine))?;
-                } else {
-                    // `line_buffer` contained only 1 word
-                    line_buffer.clear();
-                    stdout()
-                        .queue(cursor::MoveToColumn(0))?
-                        .queue(terminal::Clear(ClearType::CurrentLine))?;
-                    print_prompt()?;
-                }
-
-                stdout().flush()?;
-            }
-            KeyEvent {
-                code: KeyCode::Backspace,
-                ..
-            } => {
-                line_buffer.pop();
-                stdout()
-                    .queue(cursor::MoveLeft(1))?
-                    .queue(terminal::Clear(ClearType::UntilNewLine))?
-                    .flush()?;
-            }
-            KeyEvent {
-                code: KeyCode::Char(c),
-                ..
-            } => {
-                line_buffer.push(c);
-                input = Some(c);
+        editor.remember(input);
+    }
+}
+
+// Reads the words stored in the file at `path`, one per line, a missing file has no words
+fn load_words(path: &Path) -> Result<Trie> {
+    match File::open(path) {
+        Ok(file) => Ok(Trie::load_from_reader(file)?),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Trie::new()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+struct LineEditor {
+    history: Trie,
+    // The lines in the history in the order they were entered, oldest first, which are recalled
+    // with Up and Down
+    entries: Vec<String>,
+    // Source of the completions listed on Tab while typing the first word of the line, the
+    // history is used for the rest of the line, or if this is `None`
+    commands: Option<Box<dyn CompletionSource>>,
+    ctrl_c: CtrlC,
+    keymap: Keymap,
+    // Only used by the vi keymap, every line starts in insert mode
+    mode: EditMode,
+    // Called with the line when Enter is pressed, to decide whether the line is accepted
+    on_accept: Option<AcceptHook>,
+    // Called with every typed character before it's inserted, rejected characters are ignored
+    on_char: Option<Box<dyn Fn(char) -> bool>>,
+    // Printed before the line, may contain wide characters
+    prompt: String,
+    // Insert the closing bracket or quote when typing an opening one
+    auto_pair: bool,
+    // Color the line depending on whether it's the start of a line in the history
+    highlight_prefix: bool,
+    // Text removed by the kill commands, most recent last, which can be yanked back
+    kill_ring: Vec<String>,
+    // Don't draw the line, nor complete or suggest anything for it, e.g. while reading a password
+    hidden: bool,
+    theme: Theme,
+    // The width of the terminal
+    width: usize,
+}
+
+// What pressing Ctrl+C while editing a line does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CtrlC {
+    // Discard the current line and start editing a new one, like bash does
+    CancelLine,
+    // End the input, as if Ctrl+D was pressed on an empty line
+    Exit,
+}
+
+// The set of key bindings used to edit a line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Keymap {
+    // The readline defaults, like Ctrl+U and Ctrl+Y
+    Emacs,
+    // Modal editing, Escape switches from inserting text to a subset of vi's normal mode commands
+    Vi,
+}
+
+// The mode of the vi keymap
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EditMode {
+    // Typed characters are inserted into the line
+    Insert,
+    // Typed characters are commands, like moving the cursor or deleting characters
+    Normal,
+}
+
+type AcceptHook = Box<dyn FnMut(&str) -> AcceptDecision>;
+
+// What happens to a line when Enter is pressed. Only `Accept` is used by the binary itself, which
+// doesn't install an `on_accept` hook.
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum AcceptDecision {
+    // Return the line from `read_line`
+    Accept,
+    // Show the message below the line, and continue editing it
+    Reject(String),
+    // Return the given line instead
+    Replace(String),
+}
+
+impl LineEditor {
+    fn new() -> Self {
+        Self {
+            history: Trie::new(),
+            entries: Vec::new(),
+            commands: None,
+            ctrl_c: CtrlC::CancelLine,
+            keymap: Keymap::Emacs,
+            mode: EditMode::Insert,
+            on_accept: None,
+            on_char: None,
+            prompt: PROMPT.to_string(),
+            auto_pair: false,
+            highlight_prefix: false,
+            kill_ring: Vec::new(),
+            hidden: false,
+            theme: Theme::default(),
+            width: 80,
+        }
+    }
+
+    #[cfg(test)]
+    fn with_commands(commands: Box<dyn CompletionSource>) -> Self {
+        Self {
+            commands: Some(commands),
+            ..Self::new()
+        }
+    }
+
+    // Returns the completions of the `word` in `line`. The first word of the line is completed
+    // from the commands, the other words from the history, most frequently entered lines first.
+    fn completions(&self, line: &str, word: Range<usize>) -> Vec<String> {
+        let prefix = &line[word.clone()];
+        match &self.commands {
+            Some(commands) if line[..word.start].trim().is_empty() => commands.complete(prefix),
+            _ => self
+                .history
+                .top_completions(prefix, MAX_COMPLETIONS)
+                .into_iter()
+                .cloned()
+                .collect(),
+        }
+    }
+
+    // Returns the text by which Tab replaces the `word` in `line` instead of listing the
+    // `completions`: the only completion, or the prefix shared by all the completions if it's
+    // longer than the `word`
+    fn expansion(&self, line: &str, word: Range<usize>, completions: &[String]) -> Option<String> {
+        if let [completion] = completions {
+            return Some(completion.clone());
+        }
+
+        let prefix = &line[word.clone()];
+        let common = match &self.commands {
+            Some(_) if line[..word.start].trim().is_empty() => {
+                Trie::from_words(completions).longest_common_prefix()
+            }
+            // Only the most frequent lines are listed, but the prefix has to be shared by all of
+            // the lines in the history
+            _ => self.history.longest_common_prefix_with(prefix),
+        };
+        Some(common).filter(|common| common.len() > prefix.len() && common.starts_with(prefix))
+    }
+
+    // Returns the rest of the most recent line in the history starting with `line`, which is
+    // suggested to complete the line
+    fn autosuggestion(&self, line: &str) -> Option<String> {
+        if line.is_empty() {
+            return None;
+        }
+
+        let opts = CompleteOptions {
+            max_results: Some(1),
+            ranking: Ranking::MostRecent,
+            ..CompleteOptions::default()
+        };
+        complete(&self.history, line, opts)
+            .pop()
+            .filter(|suggestion| suggestion.len() > line.len())
+            .map(|suggestion| suggestion[line.len()..].to_string())
+    }
+
+    // Replaces the history by `history`, whose lines are recalled in the order they were inserted
+    fn set_history(&mut self, history: Trie) {
+        self.entries = history.words_in_insertion_order().cloned().collect();
+        self.history = history;
+    }
+
+    // Adds the entered `line` to the history
+    fn remember(&mut self, line: String) {
+        self.history.insert(&line, ());
+        self.entries.push(line);
+    }
+
+    // Removes every occurrence of `line` from the history, returns whether it was present
+    fn forget(&mut self, line: &str) -> bool {
+        self.entries.retain(|entry| entry != line);
+        self.history.delete(line)
+    }
+
+    fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+        self.mode = EditMode::Insert;
+    }
+
+    // Runs the vi normal mode command `c` on the `line_buffer`, unknown commands are ignored
+    fn vi_command(&mut self, c: char, line_buffer: &mut LineBuffer) {
+        match c {
+            'h' => line_buffer.move_left(),
+            'l' => line_buffer.move_right(),
+            'w' => line_buffer.cursor = line_buffer.next_word_start(),
+            'b' => line_buffer.cursor = line_buffer.word_start(),
+            '0' => line_buffer.move_to_start(),
+            '$' => line_buffer.move_to_end(),
+            'x' => {
+                line_buffer.delete();
+            }
+            'i' => self.mode = EditMode::Insert,
+            'a' => {
+                line_buffer.move_right();
+                self.mode = EditMode::Insert;
             }
             _ => {}
         }
+    }
+
+    // Draws the state of the `search` in place of the line, with the cursor after the matched line
+    fn draw_search<W: Write>(
+        &self,
+        out: &mut W,
+        screen: &mut Screen,
+        search: &ReverseSearch,
+    ) -> Result<()> {
+        let mut matched = LineBuffer::new();
+        matched.insert_str(search.matched(&self.entries).unwrap_or_default());
+        screen.set_prompt(&search.prompt());
+        screen.redraw(out, &matched)
+    }
+
+    // Adds `killed` to the kill ring, forgetting the oldest kill if the ring is full
+    fn kill(&mut self, killed: String) {
+        if killed.is_empty() {
+            return;
+        }
 
-        if let Some(c) = input {
-            print!("{}", c);
-            stdout().flush()?;
+        if self.kill_ring.len() == KILL_RING_SIZE {
+            self.kill_ring.remove(0);
         }
+        self.kill_ring.push(killed);
+    }
+
+    // Reads a line from the terminal, returns `None` if the end of the input was reached
+    fn read_line(&mut self) -> Result<Option<String>> {
+        let (width, _) = terminal::size()?;
+        self.width = width as usize;
+        self.read_line_from(&mut stdout(), read_event)
     }
 
-    println!();
+    // Reads a line from the terminal without showing what's typed, see `read_line`
+    fn read_password(&mut self) -> Result<Option<String>> {
+        self.hidden = true;
+        let password = self.read_line();
+        self.hidden = false;
+        password
+    }
+
+    // Reads a line from the terminal, returns `None` if the line wasn't completed within `timeout`,
+    // or if the end of the input was reached
+    fn read_line_timeout(&mut self, timeout: Duration) -> Result<Option<String>> {
+        let (width, _) = terminal::size()?;
+        self.width = width as usize;
+
+        let deadline = Instant::now() + timeout;
+        self.edit_line(&mut stdout(), || {
+            if poll_event(deadline.saturating_duration_since(Instant::now()))? {
+                read_event().map(Some)
+            } else {
+                Ok(None)
+            }
+        })
+    }
+
+    fn read_line_from<W, E>(&mut self, out: &mut W, mut next_event: E) -> Result<Option<String>>
+    where
+        W: Write,
+        E: FnMut() -> crossterm::Result<Event>,
+    {
+        self.edit_line(out, || next_event().map(Some))
+    }
+
+    // Edits a line until it's completed, `next_event` returns `None` to give up on the line
+    fn edit_line<W, E>(&mut self, out: &mut W, mut next_event: E) -> Result<Option<String>>
+    where
+        W: Write,
+        E: FnMut() -> crossterm::Result<Option<Event>>,
+    {
+        print_prompt(out, &self.prompt, self.theme.prompt)?;
+
+        self.mode = EditMode::Insert;
+        let mut screen = Screen::new(self.width, &self.prompt).with_prompt_style(self.theme.prompt);
+        screen.hidden = self.hidden;
+        let mut line_buffer = LineBuffer::new();
+        // The rest of the suggested line, shown after the line while the cursor is at its end
+        let mut suggestion = None;
+        // The index into `line_buffer` at which the last yanked text starts, and the index of that
+        // text in the kill ring, as long as nothing else happened after yanking it
+        let mut yanked = None;
+        // The completions cycled through with Tab, as long as nothing else happened after Tab
+        let mut completing: Option<CompletionCycle> = None;
+        // The index into `entries` of the line recalled with Up and Down, which is the length of
+        // `entries` while editing a new line. The new line is kept in `draft` while recalling.
+        let mut recalled = self.entries.len();
+        let mut draft = String::new();
+        // The search started with Ctrl+R, which is drawn instead of the line while it's going on
+        let mut searching: Option<ReverseSearch> = None;
+        loop {
+            if self.highlight_prefix && searching.is_none() {
+                let style = if is_known_prefix(&self.history, line_buffer.as_str()) {
+                    self.theme.known_prefix
+                } else {
+                    self.theme.unknown_prefix
+                };
+                if style != screen.line_style {
+                    screen.line_style = style;
+                    screen.redraw(out, &line_buffer)?;
+                    out.flush()?;
+                }
+            }
+
+            let previous_suggestion = suggestion.take();
+            if line_buffer.tail().is_empty() && !self.hidden && searching.is_none() {
+                suggestion = self.autosuggestion(line_buffer.as_str());
+            }
+            if suggestion.is_some() || previous_suggestion.is_some() {
+                let hint = suggestion.as_deref().unwrap_or_default();
+                screen.draw_hint(out, &line_buffer, hint, self.theme.autosuggestion)?;
+                out.flush()?;
+            }
+
+            let event = match next_event()? {
+                Some(Event::Key(event)) => event,
+                Some(Event::Mouse(MouseEvent {
+                    kind: MouseEventKind::Down(MouseButton::Left),
+                    column,
+                    row,
+                    ..
+                })) => {
+                    // The click is reported in absolute coordinates, while the screen only knows
+                    // the rows relative to the prompt
+                    let (_, cursor_row) = cursor::position()?;
+                    let row = row as isize - cursor_row as isize + screen.row as isize;
+                    if searching.is_some() {
+                        continue;
+                    }
+                    if let Some(index) = screen.index_at(&line_buffer, row, column as usize) {
+                        line_buffer.cursor = index;
+                        screen.move_to(out, screen.position(line_buffer.head()))?;
+                        out.flush()?;
+                    }
+                    continue;
+                }
+                Some(Event::Resize(width, _)) => {
+                    // The terminal may have rewrapped the line, so draw it again from scratch
+                    self.width = width as usize;
+                    screen.width = self.width;
+                    match &searching {
+                        Some(search) => self.draw_search(out, &mut screen, search)?,
+                        None => screen.redraw(out, &line_buffer)?,
+                    }
+                    out.flush()?;
+                    continue;
+                }
+                Some(_) => continue,
+                None => {
+                    screen.move_to_end(out, &line_buffer)?;
+                    out.queue(Print("\r\n"))?.flush()?;
+                    return Ok(None);
+                }
+            };
+
+            let last_yank = yanked.take();
+            let last_completing = completing.take();
+            if let Some(search) = &mut searching {
+                let command = event
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+                let done = match event.code {
+                    KeyCode::Char('r') if event.modifiers == KeyModifiers::CONTROL => {
+                        search.older(&self.entries);
+                        false
+                    }
+                    KeyCode::Char('c' | 'g') if event.modifiers == KeyModifiers::CONTROL => true,
+                    KeyCode::Char(c) if !command => {
+                        search.push(c, &self.entries);
+                        false
+                    }
+                    KeyCode::Backspace => {
+                        search.pop(&self.entries);
+                        false
+                    }
+                    KeyCode::Enter => {
+                        if let Some(line) = search.matched(&self.entries) {
+                            line_buffer.replace(line.to_string());
+                        }
+                        true
+                    }
+                    KeyCode::Esc => true,
+                    _ => false,
+                };
+
+                if done {
+                    searching = None;
+                    screen.set_prompt(&self.prompt);
+                    screen.redraw(out, &line_buffer)?;
+                } else {
+                    self.draw_search(out, &mut screen, search)?;
+                }
+                out.flush()?;
+                continue;
+            }
+
+            if self.keymap == Keymap::Vi {
+                let command = !event
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+                let handled = match (self.mode, event.code) {
+                    (EditMode::Insert, KeyCode::Esc) => {
+                        // Like vi, leave the cursor on the last inserted character
+                        self.mode = EditMode::Normal;
+                        line_buffer.move_left();
+                        true
+                    }
+                    (EditMode::Normal, KeyCode::Char(c)) if command => {
+                        self.vi_command(c, &mut line_buffer);
+                        true
+                    }
+                    _ => false,
+                };
+
+                if handled {
+                    screen.redraw(out, &line_buffer)?;
+                    out.flush()?;
+                    continue;
+                }
+            }
+
+            match event {
+                KeyEvent {
+                    modifiers: KeyModifiers::CONTROL,
+                    code: KeyCode::Char(kill @ ('u' | 'k' | 'w')),
+                } => {
+                    let range = match kill {
+                        'u' => 0..line_buffer.cursor,
+                        'k' => line_buffer.cursor..line_buffer.as_str().len(),
+                        _ => line_buffer.word_start()..line_buffer.cursor,
+                    };
+                    let killed = line_buffer.remove(range);
+                    // A hidden line shouldn't be yanked back into a line which is shown
+                    if !self.hidden {
+                        self.kill(killed);
+                    }
+
+                    screen.redraw(out, &line_buffer)?;
+                    out.flush()?;
+                }
+                KeyEvent {
+                    modifiers: KeyModifiers::CONTROL,
+                    code: KeyCode::Char('r'),
+                } if !self.hidden => {
+                    let search = ReverseSearch::new();
+                    self.draw_search(out, &mut screen, &search)?;
+                    out.flush()?;
+                    searching = Some(search);
+                }
+                KeyEvent {
+                    code: KeyCode::Home,
+                    ..
+                }
+                | KeyEvent {
+                    modifiers: KeyModifiers::CONTROL,
+                    code: KeyCode::Char('a'),
+                } => {
+                    line_buffer.move_to_start();
+                    screen.move_to(out, screen.position(line_buffer.head()))?;
+                    out.flush()?;
+                }
+                KeyEvent {
+                    code: KeyCode::End, ..
+                }
+                | KeyEvent {
+                    modifiers: KeyModifiers::CONTROL,
+                    code: KeyCode::Char('e'),
+                } => {
+                    // A suggestion is only shown while the cursor is at the end of the line, in
+                    // which case there's nowhere to move to and End accepts the suggestion instead
+                    match suggestion.take() {
+                        Some(suggestion) => {
+                            line_buffer.insert_str(&suggestion);
+                            screen.redraw(out, &line_buffer)?;
+                        }
+                        None => {
+                            line_buffer.move_to_end();
+                            screen.move_to(out, screen.position(line_buffer.head()))?;
+                        }
+                    }
+                    out.flush()?;
+                }
+                KeyEvent {
+                    modifiers: KeyModifiers::CONTROL,
+                    code: KeyCode::Char('l'),
+                } => {
+                    // Clear the terminal, and draw the line at the top, with the cursor where it was
+                    out.queue(terminal::Clear(ClearType::All))?
+                        .queue(cursor::MoveTo(0, 0))?;
+                    screen.row = 0;
+                    screen.redraw(out, &line_buffer)?;
+                    out.flush()?;
+                }
+                KeyEvent {
+                    modifiers: KeyModifiers::CONTROL,
+                    code: KeyCode::Char('t'),
+                } => {
+                    if !line_buffer.transpose() {
+                        continue;
+                    }
+
+                    screen.redraw(out, &line_buffer)?;
+                    out.flush()?;
+                }
+                KeyEvent {
+                    modifiers: KeyModifiers::CONTROL,
+                    code: KeyCode::Char('y'),
+                } => {
+                    if let Some(killed) = self.kill_ring.last() {
+                        yanked = Some((line_buffer.cursor, self.kill_ring.len() - 1));
+                        line_buffer.insert_str(killed);
+                        screen.redraw(out, &line_buffer)?;
+                        out.flush()?;
+                    }
+                }
+                KeyEvent {
+                    modifiers: KeyModifiers::ALT,
+                    code: KeyCode::Char(case @ ('u' | 'l' | 'c')),
+                } => {
+                    line_buffer.change_word(match case {
+                        'u' => str::to_uppercase,
+                        'l' => str::to_lowercase,
+                        _ => capitalize,
+                    });
+
+                    screen.redraw(out, &line_buffer)?;
+                    out.flush()?;
+                }
+                KeyEvent {
+                    modifiers: KeyModifiers::ALT,
+                    code: KeyCode::Char('y'),
+                } => {
+                    // Right after yanking, replace the yanked text by the kill before it
+                    if let Some((start, index)) = last_yank {
+                        let index = index.checked_sub(1).unwrap_or(self.kill_ring.len() - 1);
+                        line_buffer.remove(start..line_buffer.cursor);
+                        line_buffer.insert_str(&self.kill_ring[index]);
+                        yanked = Some((start, index));
+
+                        screen.redraw(out, &line_buffer)?;
+                        out.flush()?;
+                    }
+                }
+                KeyEvent {
+                    modifiers: KeyModifiers::CONTROL,
+                    code: KeyCode::Char('c'),
+                } => match self.ctrl_c {
+                    CtrlC::CancelLine => {
+                        screen.move_to_end(out, &line_buffer)?;
+                        line_buffer.clear();
+                        recalled = self.entries.len();
+                        out.queue(Print("^C\r\n"))?;
+                        screen.row = 0;
+                        print_prompt(out, &self.prompt, self.theme.prompt)?;
+                    }
+                    CtrlC::Exit => {
+                        screen.move_to_end(out, &line_buffer)?;
+                        out.queue(Print("\r\n"))?.flush()?;
+                        return Ok(None);
+                    }
+                },
+                KeyEvent {
+                    modifiers: KeyModifiers::CONTROL,
+                    code: KeyCode::Char('d'),
+                } => {
+                    // Like in a shell, Ctrl+D on an empty line signals the end of the input, on a
+                    // non-empty line it deletes the character under the cursor
+                    if line_buffer.is_empty() {
+                        out.queue(Print("\r\n"))?.flush()?;
+                        return Ok(None);
+                    }
+
+                    if line_buffer.delete().is_some() {
+                        screen.redraw(out, &line_buffer)?;
+                        out.flush()?;
+                    }
+                }
+                KeyEvent {
+                    code: KeyCode::Enter,
+                    ..
+                } => {
+                    let decision = match &mut self.on_accept {
+                        Some(on_accept) => on_accept(line_buffer.as_str()),
+                        None => AcceptDecision::Accept,
+                    };
+
+                    match decision {
+                        AcceptDecision::Accept => break,
+                        AcceptDecision::Reject(message) => {
+                            // Show the message below the current line, and redraw the line below it
+                            screen.move_to_end(out, &line_buffer)?;
+                            out.queue(Print("\r\n"))?
+                                .queue(Print(message))?
+                                .queue(Print("\r\n"))?;
+                            screen.row = 0;
+                            screen.redraw(out, &line_buffer)?;
+                            out.flush()?;
+                        }
+                        AcceptDecision::Replace(line) => {
+                            line_buffer.replace(line);
+                            screen.redraw(out, &line_buffer)?;
+                            break;
+                        }
+                    }
+                }
+                KeyEvent {
+                    modifiers: KeyModifiers::CONTROL,
+                    code: KeyCode::Backspace,
+                } => {
+                    let start = previous_word_boundary(line_buffer.head());
+                    if start == line_buffer.cursor {
+                        continue;
+                    }
+
+                    line_buffer.remove(start..line_buffer.cursor);
+                    screen.redraw(out, &line_buffer)?;
+                    out.flush()?;
+                }
+                KeyEvent {
+                    code: KeyCode::Backspace,
+                    ..
+                } => {
+                    if let Some(removed) = line_buffer.backspace() {
+                        screen.backspace(out, &line_buffer, &removed)?;
+                        out.flush()?;
+                    }
+                }
+                KeyEvent {
+                    code: code @ (KeyCode::Tab | KeyCode::BackTab),
+                    ..
+                } if !self.hidden => {
+                    // Pressing Tab again replaces the word by the next completion, Shift+Tab by the
+                    // previous one
+                    if let Some(mut cycle) = last_completing {
+                        let range = cycle.range();
+                        let word = match code {
+                            KeyCode::Tab => cycle.forward(),
+                            _ => cycle.backward(),
+                        };
+                        line_buffer.replace_range(range, word);
+                        screen.redraw(out, &line_buffer)?;
+                        out.flush()?;
+                        completing = Some(cycle);
+                        continue;
+                    }
+
+                    // Only the word under the cursor is completed, the rest of the line is kept
+                    let word = current_word(line_buffer.as_str(), line_buffer.cursor);
+                    let typed = line_buffer.as_str()[word.clone()].to_string();
+                    let completions = self.completions(line_buffer.as_str(), word.clone());
+                    // The word is first completed as far as it can be without choosing between the
+                    // completions, only then are they listed
+                    if let Some(expansion) =
+                        self.expansion(line_buffer.as_str(), word.clone(), &completions)
+                    {
+                        line_buffer.replace_range(word, &expansion);
+                        screen.redraw(out, &line_buffer)?;
+                        out.flush()?;
+                    } else if !completions.is_empty() {
+                        // List the completions below the current line, and redraw the line below
+                        // them
+                        screen.move_to_end(out, &line_buffer)?;
+                        for row in layout_columns(&completions, self.width) {
+                            out.queue(Print("\r\n"))?;
+                            for (completion, padding) in row {
+                                queue_completion(out, completion, &typed, &self.theme)?;
+                                out.queue(Print(" ".repeat(padding)))?;
+                            }
+                        }
+                        out.queue(Print("\r\n"))?;
+                        screen.row = 0;
+                        screen.redraw(out, &line_buffer)?;
+                        out.flush()?;
+
+                        completing = Some(CompletionCycle::new(&typed, word.start, completions));
+                    }
+                }
+                KeyEvent {
+                    code: KeyCode::Char(c),
+                    ..
+                } => {
+                    if self.on_char.as_ref().is_some_and(|on_char| !on_char(c)) {
+                        continue;
+                    }
+
+                    let inserted = if self.auto_pair {
+                        line_buffer.insert_paired(c)
+                    } else {
+                        line_buffer.insert(c);
+                        true
+                    };
+
+                    if inserted {
+                        screen.insert(out, &line_buffer, c)?;
+                    } else {
+                        screen.move_to(out, screen.position(line_buffer.head()))?;
+                    }
+                    out.flush()?;
+
+                    // Editing a recalled line turns it into a new line
+                    recalled = self.entries.len();
+                }
+                KeyEvent {
+                    code: KeyCode::Up, ..
+                } if !self.hidden => {
+                    if recalled == 0 {
+                        continue;
+                    }
+
+                    if recalled == self.entries.len() {
+                        draft = line_buffer.as_str().to_string();
+                    }
+                    recalled -= 1;
+                    line_buffer.replace(self.entries[recalled].clone());
+                    screen.redraw(out, &line_buffer)?;
+                    out.flush()?;
+                }
+                KeyEvent {
+                    code: KeyCode::Down,
+                    ..
+                } if !self.hidden => {
+                    if recalled >= self.entries.len() {
+                        continue;
+                    }
+
+                    recalled += 1;
+                    let line = match self.entries.get(recalled) {
+                        Some(entry) => entry.clone(),
+                        None => mem::take(&mut draft),
+                    };
+                    line_buffer.replace(line);
+                    screen.redraw(out, &line_buffer)?;
+                    out.flush()?;
+                }
+                KeyEvent {
+                    code: KeyCode::Right,
+                    ..
+                } => {
+                    // A suggestion is only shown while the cursor is at the end of the line, so
+                    // Right can either accept it or move the cursor
+                    match suggestion.take() {
+                        Some(suggestion) => {
+                            line_buffer.insert_str(&suggestion);
+                            screen.redraw(out, &line_buffer)?;
+                        }
+                        None => {
+                            line_buffer.move_right();
+                            screen.move_to(out, screen.position(line_buffer.head()))?;
+                        }
+                    }
+                    out.flush()?;
+                }
+                KeyEvent {
+                    code: KeyCode::Left,
+                    ..
+                } => {
+                    line_buffer.move_left();
+                    screen.move_to(out, screen.position(line_buffer.head()))?;
+                    out.flush()?;
+                }
+                _ => {}
+            }
+        }
+
+        screen.move_to_end(out, &line_buffer)?;
+        out.queue(Print("\r\n"))?.flush()?;
 
-    Ok(line_buffer)
+        Ok(Some(line_buffer.into_string()))
+    }
 }
 
-fn print_prompt() -> Result<()> {
-    stdout().queue(Print("> ".yellow()))?.flush()?;
+// Keeps track of where the line being edited is drawn on the terminal, taking into account that
+// lines longer than the terminal are wrapped onto multiple rows
+#[derive(Debug)]
+struct Screen {
+    width: usize,
+    prompt: String,
+    prompt_style: ContentStyle,
+    // The number of columns the prompt occupies
+    prompt_width: usize,
+    // The row of the terminal cursor, relative to the row the prompt was printed on
+    row: usize,
+    // Draw the line as if it were empty
+    hidden: bool,
+    // The style in which the line is drawn
+    line_style: ContentStyle,
+}
 
-    Ok(())
+impl Screen {
+    fn new(width: usize, prompt: &str) -> Self {
+        Self {
+            width,
+            prompt: prompt.to_string(),
+            prompt_style: Theme::default().prompt,
+            prompt_width: str_width(prompt),
+            row: 0,
+            hidden: false,
+            line_style: ContentStyle::new(),
+        }
+    }
+
+    fn with_prompt_style(self, prompt_style: ContentStyle) -> Self {
+        Self {
+            prompt_style,
+            ..self
+        }
+    }
+
+    // Replaces the prompt, which is drawn on the next redraw
+    fn set_prompt(&mut self, prompt: &str) {
+        self.prompt = prompt.to_string();
+        self.prompt_width = str_width(prompt);
+    }
+
+    // Returns the row and column at which the character following `text` is drawn, where `text`
+    // starts right after the prompt
+    fn position(&self, text: &str) -> (usize, usize) {
+        self.positions(text).last().map_or_else(
+            || self.start(),
+            |(_, (row, column), width)| self.advance((row, column), width),
+        )
+    }
+
+    // The row and column at which the first character after the prompt is drawn
+    fn start(&self) -> (usize, usize) {
+        let width = self.width.max(1);
+        (self.prompt_width / width, self.prompt_width % width)
+    }
+
+    // Returns the position following a character of `width` columns drawn at `row` and `column`
+    fn advance(&self, (row, column): (usize, usize), width: usize) -> (usize, usize) {
+        if column + width >= self.width.max(1) {
+            (row + 1, 0)
+        } else {
+            (row, column + width)
+        }
+    }
+
+    // Returns the index, the row and column at which it is drawn, and the display width of every
+    // character in `text`, where `text` starts right after the prompt
+    fn positions<'a>(
+        &'a self,
+        text: &'a str,
+    ) -> impl Iterator<Item = (usize, (usize, usize), usize)> + 'a {
+        let text = if self.hidden { "" } else { text };
+        let mut position = self.start();
+        text.char_indices().map(move |(index, c)| {
+            let width = char_width(c);
+            // A character which doesn't fit on the current row is drawn on the next one
+            if position.1 + width > self.width.max(1) {
+                position = (position.0 + 1, 0);
+            }
+
+            let drawn_at = position;
+            position = self.advance(position, width);
+            (index, drawn_at, width)
+        })
+    }
+
+    // Returns the index into the `line_buffer` of the character drawn at `row` and `column`, or the
+    // end of the line if nothing is drawn there. Clicking on the prompt returns the start of the
+    // line, returns `None` if `row` is outside the rows the line is drawn on.
+    fn index_at(&self, line_buffer: &LineBuffer, row: isize, column: usize) -> Option<usize> {
+        let (last_row, _) = self.position(line_buffer.as_str());
+        if row < 0 || row as usize > last_row {
+            return None;
+        }
+
+        let row = row as usize;
+        let index = self
+            .positions(line_buffer.as_str())
+            .find(|(_, (r, c), width)| *r > row || (*r == row && c + width > column))
+            .map_or(line_buffer.as_str().len(), |(index, _, _)| index);
+        Some(index)
+    }
+
+    // Moves the terminal cursor from the current row to `position`
+    fn move_to<W: Write>(&mut self, out: &mut W, (row, column): (usize, usize)) -> Result<()> {
+        if row < self.row {
+            out.queue(cursor::MoveUp((self.row - row) as u16))?;
+        } else if row > self.row {
+            out.queue(cursor::MoveDown((row - self.row) as u16))?;
+        }
+        // The column of `MoveToColumn` ends up in a 1-based escape sequence
+        out.queue(cursor::MoveToColumn(column as u16 + 1))?;
+        self.row = row;
+
+        Ok(())
+    }
+
+    // Moves the terminal cursor to the end of the line, and clears whatever was drawn after it
+    fn move_to_end<W: Write>(&mut self, out: &mut W, line_buffer: &LineBuffer) -> Result<()> {
+        self.move_to(out, self.position(line_buffer.as_str()))?;
+        out.queue(terminal::Clear(ClearType::FromCursorDown))?;
+
+        Ok(())
+    }
+
+    // Draws the `hint` after the end of the line, replacing any hint drawn before, and moves the
+    // terminal cursor back to the cursor position of the `line_buffer`
+    fn draw_hint<W: Write>(
+        &mut self,
+        out: &mut W,
+        line_buffer: &LineBuffer,
+        hint: &str,
+        style: ContentStyle,
+    ) -> Result<()> {
+        self.move_to_end(out, line_buffer)?;
+        out.queue(Print(style.apply(hint)))?;
+        self.wrap(out, &format!("{}{}", line_buffer.as_str(), hint))?;
+
+        self.move_to(out, self.position(line_buffer.head()))
+    }
+
+    // Draws `c`, which was just inserted into `line_buffer`
+    fn insert<W: Write>(&mut self, out: &mut W, line_buffer: &LineBuffer, c: char) -> Result<()> {
+        if self.hidden {
+            return Ok(());
+        }
+        if !line_buffer.tail().is_empty() {
+            return self.redraw(out, line_buffer);
+        }
+
+        out.queue(Print(self.line_style.apply(c)))?;
+        self.wrap(out, line_buffer.as_str())
+    }
+
+    // Erases the `removed` grapheme, which was just removed from before the cursor of `line_buffer`
+    fn backspace<W: Write>(
+        &mut self,
+        out: &mut W,
+        line_buffer: &LineBuffer,
+        removed: &str,
+    ) -> Result<()> {
+        if self.hidden {
+            return Ok(());
+        }
+
+        // Only when the cursor stays on the same row at the end of the line, it's enough to erase
+        // the columns of the `removed` grapheme, which are more than one for wide characters
+        let width = str_width(removed);
+        let (row, _) = self.position(line_buffer.head());
+        if !line_buffer.tail().is_empty() || row != self.row || width == 0 {
+            return self.redraw(out, line_buffer);
+        }
+
+        out.queue(cursor::MoveLeft(width as u16))?
+            .queue(terminal::Clear(ClearType::UntilNewLine))?;
+
+        Ok(())
+    }
+
+    // After printing the last column of a row, terminals keep the cursor on that row until the
+    // next character is printed, so move it to the next row ourselves to keep `row` correct
+    fn wrap<W: Write>(&mut self, out: &mut W, printed: &str) -> Result<()> {
+        let (row, column) = self.position(printed);
+        if column == 0 && row > 0 {
+            out.queue(Print("\r\n"))?;
+        }
+        self.row = row;
+
+        Ok(())
+    }
+
+    // Clears the line, and prints the prompt and the line again, leaving the cursor at the cursor
+    // position of the `line_buffer`
+    fn redraw<W: Write>(&mut self, out: &mut W, line_buffer: &LineBuffer) -> Result<()> {
+        self.move_to(out, (0, 0))?;
+        out.queue(terminal::Clear(ClearType::FromCursorDown))?;
+        print_prompt(out, &self.prompt, self.prompt_style)?;
+        if !self.hidden {
+            out.queue(Print(self.line_style.apply(line_buffer.as_str())))?;
+        }
+        self.wrap(out, line_buffer.as_str())?;
+
+        self.move_to(out, self.position(line_buffer.head()))
+    }
 }
 
-type Result<R, E = Error> = std::result::Result<R, E>;
+// The line being edited, together with the position of the cursor in that line
+#[derive(Debug, Default)]
+struct LineBuffer {
+    buffer: String,
+    // Byte index into `buffer`, always on a character boundary
+    cursor: usize,
+}
 
-#[derive(Debug, Error)]
-pub enum Error {
-    #[error(transparent)]
-    IoError(#[from] io::Error),
+impl LineBuffer {
+    fn new() -> Self {
+        Self::default()
+    }
 
-    #[error(transparent)]
-    CrosstermError(#[from] crossterm::ErrorKind),
+    fn as_str(&self) -> &str {
+        &self.buffer
+    }
+
+    fn into_string(self) -> String {
+        self.buffer
+    }
+
+    fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    // The part of the line before the cursor
+    fn head(&self) -> &str {
+        &self.buffer[..self.cursor]
+    }
+
+    // The part of the line after the cursor
+    fn tail(&self) -> &str {
+        &self.buffer[self.cursor..]
+    }
+
+    // Inserts `c` before the cursor
+    fn insert(&mut self, c: char) {
+        self.buffer.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    // Inserts `c` before the cursor, and the character closing it after the cursor if `c` opens a
+    // pair of brackets or quotes. If `c` closes a pair and is already under the cursor, the cursor
+    // moves past it instead. Returns whether `c` was inserted.
+    fn insert_paired(&mut self, c: char) -> bool {
+        if self.tail().starts_with(c) && matches!(c, ')' | ']' | '}' | '"' | '\'') {
+            self.cursor += c.len_utf8();
+            return false;
+        }
+
+        // A quote following a letter is more likely an apostrophe than the start of a string
+        let after_word = self.head().ends_with(char::is_alphanumeric);
+        let closing = match c {
+            '(' => Some(')'),
+            '[' => Some(']'),
+            '{' => Some('}'),
+            '"' | '\'' if !after_word => Some(c),
+            _ => None,
+        };
+
+        self.insert(c);
+        if let Some(closing) = closing {
+            self.buffer.insert(self.cursor, closing);
+        }
+        true
+    }
+
+    // Inserts `s` before the cursor
+    fn insert_str(&mut self, s: &str) {
+        self.buffer.insert_str(self.cursor, s);
+        self.cursor += s.len();
+    }
+
+    fn move_to_start(&mut self) {
+        self.cursor = 0;
+    }
+
+    fn move_to_end(&mut self) {
+        self.cursor = self.buffer.len();
+    }
+
+    // Moves the cursor to the previous grapheme
+    fn move_left(&mut self) {
+        self.cursor = previous_grapheme(&self.buffer, self.cursor).unwrap_or(self.cursor);
+    }
+
+    // Moves the cursor to the next grapheme
+    fn move_right(&mut self) {
+        self.cursor = next_grapheme(&self.buffer, self.cursor).unwrap_or(self.cursor);
+    }
+
+    // The index of the start of the next word after the cursor, or the end of the line if there
+    // is none
+    fn next_word_start(&self) -> usize {
+        let tail = self.tail();
+        let rest = tail
+            .trim_start_matches(|c: char| !c.is_whitespace())
+            .trim_start_matches(char::is_whitespace);
+        self.buffer.len() - rest.len()
+    }
+
+    // Swaps the grapheme before the cursor with the one under it, and moves the cursor past both.
+    // At the end of the line, the last two graphemes are swapped instead. Returns whether anything
+    // was swapped.
+    fn transpose(&mut self) -> bool {
+        let end = next_grapheme(&self.buffer, self.cursor).unwrap_or(self.cursor);
+        let middle = match previous_grapheme(&self.buffer, end) {
+            Some(middle) => middle,
+            None => return false,
+        };
+        let start = match previous_grapheme(&self.buffer, middle) {
+            Some(start) => start,
+            None => return false,
+        };
+
+        let second = self.remove(middle..end);
+        self.buffer.insert_str(start, &second);
+        self.cursor = end;
+        true
+    }
+
+    // The index of the start of the word before the cursor, skipping whitespace before the cursor
+    fn word_start(&self) -> usize {
+        self.head()
+            .trim_end_matches(char::is_whitespace)
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map_or(0, |(index, c)| index + c.len_utf8())
+    }
+
+    // The index of the end of the word after the cursor, skipping whitespace after the cursor
+    fn word_end(&self) -> usize {
+        let tail = self.tail();
+        let word = tail.trim_start_matches(char::is_whitespace);
+        let start = self.cursor + tail.len() - word.len();
+        word.find(char::is_whitespace)
+            .map_or(self.buffer.len(), |index| start + index)
+    }
+
+    // Replaces the word after the cursor by `change` applied to it, and moves the cursor past it
+    fn change_word(&mut self, change: impl Fn(&str) -> String) {
+        let end = self.word_end();
+        let word = self.remove(self.cursor..end);
+        self.insert_str(&change(&word));
+    }
+
+    // Removes and returns the text in `range`, keeping the cursor on the same character
+    fn remove(&mut self, range: Range<usize>) -> String {
+        if self.cursor >= range.end {
+            self.cursor -= range.len();
+        } else if self.cursor > range.start {
+            self.cursor = range.start;
+        }
+
+        self.buffer.drain(range).collect()
+    }
+
+    // Removes the grapheme before the cursor
+    fn backspace(&mut self) -> Option<String> {
+        let start = previous_grapheme(&self.buffer, self.cursor)?;
+        let removed = self.buffer.drain(start..self.cursor).collect();
+        self.cursor = start;
+        Some(removed)
+    }
+
+    // Removes the grapheme under the cursor
+    fn delete(&mut self) -> Option<String> {
+        let end = next_grapheme(&self.buffer, self.cursor)?;
+        Some(self.buffer.drain(self.cursor..end).collect())
+    }
+
+    // Replaces the text in `range` by `text`, and moves the cursor past it
+    fn replace_range(&mut self, range: Range<usize>, text: &str) {
+        self.cursor = range.start + text.len();
+        self.buffer.replace_range(range, text);
+    }
+
+    // Replaces the whole line by `line`, and moves the cursor to its end
+    fn replace(&mut self, line: String) {
+        self.cursor = line.len();
+        self.buffer = line;
+    }
+
+    fn clear(&mut self) {
+        self.buffer.clear();
+        self.cursor = 0;
+    }
+}
+
+// The completions of a typed word, which repeated Tab presses cycle through. The typed word comes
+// after the last completion and before the first one.
+#[derive(Debug)]
+struct CompletionCycle {
+    typed: String,
+    // The index in the line at which the word starts
+    start: usize,
+    completions: Vec<String>,
+    // The index into `completions` of the completion which is shown, or `None` for the typed word
+    index: Option<usize>,
+}
+
+impl CompletionCycle {
+    fn new(typed: &str, start: usize, completions: Vec<String>) -> Self {
+        Self {
+            typed: typed.to_string(),
+            start,
+            completions,
+            index: None,
+        }
+    }
+
+    // The range in the line of the word which is shown
+    fn range(&self) -> Range<usize> {
+        self.start..self.start + self.current().len()
+    }
+
+    // Moves to the next completion, and returns the word to show
+    fn forward(&mut self) -> &str {
+        self.index = match self.index {
+            None if self.completions.is_empty() => None,
+            None => Some(0),
+            Some(index) if index + 1 < self.completions.len() => Some(index + 1),
+            Some(_) => None,
+        };
+        self.current()
+    }
+
+    // Moves to the previous completion, and returns the word to show
+    fn backward(&mut self) -> &str {
+        self.index = match self.index {
+            None => self.completions.len().checked_sub(1),
+            Some(0) => None,
+            Some(index) => Some(index - 1),
+        };
+        self.current()
+    }
+
+    fn current(&self) -> &str {
+        self.index
+            .map_or(&self.typed, |index| &self.completions[index])
+    }
+}
+
+// An incremental search through the history, most recent lines first, started with Ctrl+R. The
+// history trie doesn't know the order in which the lines were entered, so the entries are searched
+// instead.
+#[derive(Debug, Default)]
+struct ReverseSearch {
+    query: String,
+    // The index into the entries of the matched line
+    index: Option<usize>,
+    // Whether the last change to the query didn't match any line, in which case the previous
+    // match is kept
+    failed: bool,
+}
+
+impl ReverseSearch {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    // Appends `c` to the query, and matches the most recent line containing it, starting at the
+    // current match
+    fn push(&mut self, c: char, entries: &[String]) {
+        self.query.push(c);
+        let end = self.index.map_or(entries.len(), |index| index + 1);
+        self.search(entries, end);
+    }
+
+    // Removes the last character from the query, and matches the most recent line containing it
+    fn pop(&mut self, entries: &[String]) {
+        self.query.pop();
+        self.index = None;
+        self.search(entries, entries.len());
+    }
+
+    // Matches the next older line containing the query
+    fn older(&mut self, entries: &[String]) {
+        let end = self.index.unwrap_or(entries.len());
+        self.search(entries, end);
+    }
+
+    // Matches the most recent line containing the query among the first `end` entries
+    fn search(&mut self, entries: &[String], end: usize) {
+        match entries[..end]
+            .iter()
+            .rposition(|entry| entry.contains(&self.query))
+        {
+            Some(index) => {
+                self.index = Some(index);
+                self.failed = false;
+            }
+            None => self.failed = true,
+        }
+    }
+
+    fn matched<'a>(&self, entries: &'a [String]) -> Option<&'a str> {
+        self.index.map(|index| entries[index].as_str())
+    }
+
+    // The text shown before the matched line, like bash does
+    fn prompt(&self) -> String {
+        let failed = if self.failed { "failed " } else { "" };
+        format!("({}reverse-i-search)`{}': ", failed, self.query)
+    }
+}
+
+// Lays out the `items` in columns which fit within `width`, like `ls` does. The items are ordered
+// top to bottom, then left to right. Returns the rows, each item in a row is paired with the
+// number of spaces which should follow it. If an item is wider than `width`, every item is put on
+// its own row.
+fn layout_columns(items: &[String], width: usize) -> Vec<Vec<(&str, usize)>> {
+    const GAP: usize = 2;
+
+    let lens = items.iter().map(|item| str_width(item)).collect::<Vec<_>>();
+
+    // Try to fit as many columns as possible, the widest layout which fits wins
+    let mut layout = None;
+    for columns in (1..=items.len()).rev() {
+        let rows = items.len().div_ceil(columns);
+        let widths = lens
+            .chunks(rows)
+            .map(|column| column.iter().max().copied().unwrap_or(0))
+            .collect::<Vec<_>>();
+
+        let total = widths.iter().sum::<usize>() + GAP * (widths.len() - 1);
+        if total <= width || columns == 1 {
+            layout = Some((rows, widths));
+            break;
+        }
+    }
+
+    let (rows, widths) = match layout {
+        Some(layout) => layout,
+        None => return vec![],
+    };
+
+    (0..rows)
+        .map(|row| {
+            let mut line = (0..widths.len())
+                .filter_map(|column| {
+                    let index = column * rows + row;
+                    items
+                        .get(index)
+                        .map(|item| (item.as_str(), widths[column] - lens[index] + GAP))
+                })
+                .collect::<Vec<_>>();
+            if let Some((_, padding)) = line.last_mut() {
+                *padding = 0;
+            }
+            line
+        })
+        .collect()
+}
+
+// Queues the `completion` in the suggestion style of the `theme`, highlighting the characters which
+// matched the `prefix`
+fn queue_completion<W: Write>(
+    out: &mut W,
+    completion: &str,
+    prefix: &str,
+    theme: &Theme,
+) -> Result<()> {
+    let opts = CompleteOptions {
+        fuzzy: true,
+        ..CompleteOptions::default()
+    };
+    let matched = match_positions(prefix, completion, opts).unwrap_or_default();
+
+    for (position, c) in completion.chars().enumerate() {
+        if matched.contains(&position) {
+            out.queue(Print(theme.matched.apply(c)))?;
+        } else {
+            out.queue(Print(theme.suggestion.apply(c)))?;
+        }
+    }
+
+    Ok(())
+}
+
+// Returns the number of columns `c` occupies when drawn on the terminal
+//
+// This approximates the East Asian Width property: CJK characters, Hangul and most emoji occupy two
+// columns, combining marks and other zero-width characters don't occupy any.
+fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x0000..=0x001F
+        | 0x007F..=0x009F
+        | 0x0300..=0x036F
+        | 0x0483..=0x0489
+        | 0x0591..=0x05BD
+        | 0x0610..=0x061A
+        | 0x064B..=0x065F
+        | 0x1AB0..=0x1AFF
+        | 0x1DC0..=0x1DFF
+        | 0x200B..=0x200F
+        | 0x20D0..=0x20FF
+        | 0xFE00..=0xFE0F
+        | 0xFE20..=0xFE2F
+        | 0xFEFF => 0,
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+// Returns the number of columns `s` occupies when drawn on the terminal
+fn str_width(s: &str) -> usize {
+    s.chars().map(char_width).sum()
+}
+
+thread_local! {
+    // The event read by `read_event` to look at what follows the previous event, which is returned
+    // by the next call
+    static NEXT_EVENT: RefCell<Option<Event>> = const { RefCell::new(None) };
+}
+
+// Reads the next event from the terminal, see `paste_enter`
+fn read_event() -> crossterm::Result<Event> {
+    let event = match NEXT_EVENT.with(|next| next.borrow_mut().take()) {
+        Some(event) => event,
+        None => read()?,
+    };
+    let next = if poll(Duration::from_secs(0))? {
+        Some(read()?)
+    } else {
+        None
+    };
+    let event = paste_enter(event, next.as_ref());
+    NEXT_EVENT.with(|slot| *slot.borrow_mut() = next);
+    Ok(event)
+}
+
+// Returns whether `read_event` returns an event without blocking, waiting up to `timeout` for one
+fn poll_event(timeout: Duration) -> crossterm::Result<bool> {
+    if NEXT_EVENT.with(|next| next.borrow().is_some()) {
+        return Ok(true);
+    }
+    poll(timeout)
+}
+
+// This version of crossterm doesn't support bracketed paste, so pasted text arrives as a burst of
+// key events. An Enter which is immediately followed by another key event is taken to be a newline
+// in pasted text, and is turned into a space, rather than accepting the line halfway the paste.
+// Other events, like a resize or mouse event arriving right after Enter, don't affect it.
+//
+// This is a heuristic: a key which is already pending when Enter is read, e.g. because it was
+// typed ahead quickly or Enter is held down, can't be told apart from pasted text, so that Enter
+// is turned into a space as well.
+fn paste_enter(event: Event, next: Option<&Event>) -> Event {
+    match event {
+        Event::Key(KeyEvent {
+            code: KeyCode::Enter,
+            modifiers,
+        }) if matches!(next, Some(Event::Key(_))) => {
+            Event::Key(KeyEvent::new(KeyCode::Char(' '), modifiers))
+        }
+        event => event,
+    }
+}
+
+// Returns whether `line` is the start of any line in the `history`
+fn is_known_prefix(history: &Trie, line: &str) -> bool {
+    history.starts_with(line)
+}
+
+// Returns the index in `text` at which the last word starts, including the whitespace before it,
+// which Ctrl+Backspace removes up to the end of `text`. Whitespace after the last word is skipped.
+fn previous_word_boundary(text: &str) -> usize {
+    text.trim_end_matches(char::is_whitespace)
+        .trim_end_matches(|c: char| !c.is_whitespace())
+        .trim_end_matches(char::is_whitespace)
+        .len()
+}
+
+// Returns the range in `line` of the word under the `cursor`, which Tab completes. The word is
+// bounded by whitespace, so right after whitespace the range is empty.
+fn current_word(line: &str, cursor: usize) -> Range<usize> {
+    let start = line[..cursor]
+        .trim_end_matches(|c: char| !c.is_whitespace())
+        .len();
+    let end = line.len()
+        - line[cursor..]
+            .trim_start_matches(|c: char| !c.is_whitespace())
+            .len();
+    start..end
+}
+
+// Returns `word` with its first letter in uppercase, and the rest in lowercase
+fn capitalize(word: &str) -> String {
+    let mut capitalized = String::with_capacity(word.len());
+    let mut chars = word.chars();
+    for c in chars.by_ref() {
+        if c.is_alphabetic() {
+            capitalized.extend(c.to_uppercase());
+            break;
+        }
+        capitalized.push(c);
+    }
+    capitalized.push_str(&chars.as_str().to_lowercase());
+
+    capitalized
+}
+
+// Returns whether `c` is drawn as part of the grapheme before it, given the character before it
+//
+// This approximates grapheme clusters: combining marks and other zero-width characters attach to
+// the character before them, and a zero width joiner attaches the character after it as well.
+fn extends_grapheme(previous: char, c: char) -> bool {
+    previous == '\u{200D}' || (char_width(c) == 0 && !c.is_control())
+}
+
+// Returns the index of the end of the grapheme starting at `index` in `text`
+fn next_grapheme(text: &str, index: usize) -> Option<usize> {
+    let mut chars = text[index..].char_indices().peekable();
+    let (_, mut previous) = chars.next()?;
+    while let Some(&(_, c)) = chars.peek() {
+        if !extends_grapheme(previous, c) {
+            break;
+        }
+        previous = c;
+        chars.next();
+    }
+
+    Some(
+        chars
+            .peek()
+            .map_or(text.len(), |(offset, _)| index + offset),
+    )
+}
+
+// Returns the index of the start of the grapheme ending at `index` in `text`
+fn previous_grapheme(text: &str, index: usize) -> Option<usize> {
+    let mut start = None;
+    let mut current = 0;
+    while let Some(end) = next_grapheme(&text[..index], current) {
+        start = Some(current);
+        current = end;
+    }
+
+    start
+}
+
+const PROMPT: &str = "> ";
+
+// The name of the file in the home directory in which the history is kept between sessions
+const HISTORY_FILE: &str = ".treeline_history";
+
+// The name of the file in the home directory from which words to complete are read at startup
+const DICTIONARY_FILE: &str = ".treeline_words";
+
+// The maximum number of kills remembered in the kill ring
+const KILL_RING_SIZE: usize = 16;
+
+// The maximum number of completions from the history listed on Tab
+const MAX_COMPLETIONS: usize = 10;
+
+// The styles in which the parts of the editor are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Theme {
+    prompt: ContentStyle,
+    // The completions listed on Tab
+    suggestion: ContentStyle,
+    // The characters of a completion which matched the line
+    matched: ContentStyle,
+    // The suggested rest of the line, taken from the history
+    autosuggestion: ContentStyle,
+    // The line while it's the start of a line in the history, or not
+    known_prefix: ContentStyle,
+    unknown_prefix: ContentStyle,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            prompt: ContentStyle::new().foreground(Color::Yellow),
+            suggestion: ContentStyle::new().foreground(Color::Grey),
+            matched: ContentStyle::new()
+                .foreground(Color::White)
+                .attribute(Attribute::Bold),
+            autosuggestion: ContentStyle::new().foreground(Color::DarkGrey),
+            known_prefix: ContentStyle::new().foreground(Color::Green),
+            unknown_prefix: ContentStyle::new().foreground(Color::Red),
+        }
+    }
+}
+
+// Parses the color in an argument like `--prompt-color=blue`, given the part before the color
+fn color_arg(arg: &str, option: &str) -> Option<Color> {
+    Color::try_from(arg.strip_prefix(option)?).ok()
+}
+
+fn print_prompt<W: Write>(out: &mut W, prompt: &str, style: ContentStyle) -> Result<()> {
+    out.queue(Print(style.apply(prompt)))?.flush()?;
+
+    Ok(())
+}
+
+type Result<R, E = Error> = std::result::Result<R, E>;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    IoError(#[from] io::Error),
+
+    #[error(transparent)]
+    CrosstermError(#[from] crossterm::ErrorKind),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        capitalize, color_arg, current_word, is_known_prefix, layout_columns, load_words,
+        paste_enter, previous_word_boundary, print_prompt, str_width, AcceptDecision,
+        CompletionCycle, CtrlC, EditMode, Keymap, LineBuffer, LineEditor, ReverseSearch, Screen,
+        Theme, KILL_RING_SIZE, MAX_COMPLETIONS,
+    };
+    use crossterm::{
+        event::{Event, KeyCode, KeyEvent, KeyModifiers},
+        style::{Color, ContentStyle},
+    };
+    use std::{env, fs, iter, ops::Range, path::Path, process};
+    use treeline::{CompletionSource, Trie};
+
+    struct Commands;
+
+    impl CompletionSource for Commands {
+        fn complete(&self, prefix: &str) -> Vec<String> {
+            ["git", "grep", "ls"]
+                .iter()
+                .filter(|command| command.starts_with(prefix))
+                .map(|command| command.to_string())
+                .collect()
+        }
+    }
+
+    #[test]
+    fn completions_from_history() {
+        let mut editor = LineEditor::new();
+        editor.history.insert("git status", ());
+        editor.history.insert("git push", ());
+        editor.history.insert("ls -la", ());
+        editor.history.insert("status", ());
+
+        assert_eq!(
+            editor.completions("git", 0..3),
+            vec!["git push", "git status"]
+        );
+        // Only the word under the cursor is completed
+        assert_eq!(editor.completions("git st", 4..6), vec!["status"]);
+        assert!(editor.completions("git pu", 4..6).is_empty());
+    }
+
+    #[test]
+    fn completions_by_frequency() {
+        let mut editor = LineEditor::new();
+        editor.history.insert("git status", ());
+        editor.history.insert("git push", ());
+        editor.history.insert("git push", ());
+        for i in 0..MAX_COMPLETIONS {
+            editor
+                .history
+                .insert(&format!("git checkout branch-{}", i), ());
+        }
+
+        let completions = editor.completions("git", 0..3);
+        assert_eq!(completions.len(), MAX_COMPLETIONS);
+        assert_eq!(completions[0], "git push");
+        assert!(!completions.contains(&"git status".to_string()));
+    }
+
+    fn read_line(editor: &mut LineEditor, events: Vec<Event>) -> Option<String> {
+        let mut events = events.into_iter();
+        editor
+            .read_line_from(&mut Vec::new(), || {
+                Ok(events.next().expect("out of events"))
+            })
+            .unwrap()
+    }
+
+    fn key(code: KeyCode) -> Event {
+        Event::Key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn ctrl(c: char) -> Event {
+        Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL))
+    }
+
+    fn alt(c: char) -> Event {
+        Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::ALT))
+    }
+
+    fn render(rows: Vec<Vec<(&str, usize)>>) -> Vec<String> {
+        rows.into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|(item, padding)| format!("{}{}", item, " ".repeat(padding)))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn read_line_with_backspace() {
+        let mut editor = LineEditor::new();
+        let events = vec![
+            key(KeyCode::Char('l')),
+            key(KeyCode::Char('x')),
+            key(KeyCode::Backspace),
+            key(KeyCode::Char('s')),
+            key(KeyCode::Enter),
+        ];
+
+        assert_eq!(read_line(&mut editor, events), Some("ls".to_string()));
+    }
+
+    #[test]
+    fn arrow_keys_move_cursor() {
+        let mut editor = LineEditor::new();
+        let events = vec![
+            key(KeyCode::Char('l')),
+            key(KeyCode::Char('s')),
+            key(KeyCode::Left),
+            key(KeyCode::Left),
+            // Left and Backspace at the start of the line are ignored
+            key(KeyCode::Left),
+            key(KeyCode::Backspace),
+            key(KeyCode::Char('c')),
+            key(KeyCode::Right),
+            key(KeyCode::Char('x')),
+            key(KeyCode::Backspace),
+            key(KeyCode::Right),
+            // Right at the end of the line is ignored
+            key(KeyCode::Right),
+            key(KeyCode::Char('!')),
+            key(KeyCode::Enter),
+        ];
+
+        assert_eq!(read_line(&mut editor, events), Some("cls!".to_string()));
+    }
+
+    #[test]
+    fn ctrl_backspace_removes_word_before_cursor() {
+        let mut editor = LineEditor::new();
+        let mut events = "git commit --amend"
+            .chars()
+            .map(|c| key(KeyCode::Char(c)))
+            .collect::<Vec<_>>();
+        events.extend(vec![key(KeyCode::Left); 8]);
+        events.push(Event::Key(KeyEvent::new(
+            KeyCode::Backspace,
+            KeyModifiers::CONTROL,
+        )));
+        events.push(key(KeyCode::Enter));
+
+        assert_eq!(
+            read_line(&mut editor, events),
+            Some("git --amend".to_string())
+        );
+    }
+
+    #[test]
+    fn recall_history_with_up_and_down() {
+        let mut editor = LineEditor::new();
+        editor.remember("ls".to_string());
+        editor.remember("pwd".to_string());
+        let events = vec![
+            key(KeyCode::Char('c')),
+            key(KeyCode::Up),
+            key(KeyCode::Up),
+            // Up at the oldest line is ignored
+            key(KeyCode::Up),
+            key(KeyCode::Down),
+            key(KeyCode::Down),
+            // Down past the new line is ignored
+            key(KeyCode::Down),
+            key(KeyCode::Char('d')),
+            key(KeyCode::Enter),
+        ];
+
+        assert_eq!(read_line(&mut editor, events), Some("cd".to_string()));
+    }
+
+    #[test]
+    fn typing_resets_recalled_line() {
+        let mut editor = LineEditor::new();
+        editor.remember("ls".to_string());
+        editor.remember("pwd".to_string());
+        let events = vec![
+            key(KeyCode::Up),
+            key(KeyCode::Up),
+            key(KeyCode::Char('a')),
+            // The edited line is the new line, so Up recalls the newest line again
+            key(KeyCode::Up),
+            key(KeyCode::Down),
+            key(KeyCode::Enter),
+        ];
+
+        assert_eq!(read_line(&mut editor, events), Some("lsa".to_string()));
+    }
+
+    #[test]
+    fn forget_line() {
+        let mut editor = LineEditor::new();
+        editor.remember("ls".to_string());
+        editor.remember("pwd".to_string());
+        editor.remember("ls".to_string());
+
+        assert!(editor.forget("ls"));
+        assert!(!editor.forget("ls"));
+        assert!(!editor.forget("p"));
+        assert_eq!(editor.entries, vec!["pwd"]);
+        assert!(!editor.history.contains("ls"));
+    }
+
+    #[test]
+    fn missing_history_file_is_empty() {
+        let history = load_words(Path::new("/nonexistent/.treeline_history")).unwrap();
+
+        assert!(history.is_empty());
+    }
+
+    #[test]
+    fn load_dictionary() {
+        let path = env::temp_dir().join(format!("treeline_words_{}", process::id()));
+        fs::write(&path, "cargo build\n\ncargo test\n").unwrap();
+        let words = load_words(&path);
+        fs::remove_file(&path).unwrap();
+
+        let mut editor = LineEditor::new();
+        editor.history += words.unwrap();
+        assert_eq!(editor.history.len(), 2);
+        assert!(editor.entries.is_empty());
+        assert_eq!(
+            editor.completions("cargo", 0..5),
+            vec!["cargo build", "cargo test"]
+        );
+    }
+
+    #[test]
+    fn recall_loaded_history() {
+        let mut editor = LineEditor::new();
+        editor.set_history(Trie::load_from_reader("ls\npwd\n".as_bytes()).unwrap());
+        let events = vec![key(KeyCode::Up), key(KeyCode::Up), key(KeyCode::Enter)];
+
+        assert_eq!(read_line(&mut editor, events), Some("ls".to_string()));
+    }
+
+    #[test]
+    fn previous_word_boundaries() {
+        assert_eq!(previous_word_boundary(""), 0);
+        assert_eq!(previous_word_boundary("git"), 0);
+        assert_eq!(previous_word_boundary("git commit"), 3);
+        assert_eq!(previous_word_boundary("git   commit"), 3);
+        assert_eq!(previous_word_boundary("git commit   "), 3);
+        assert_eq!(previous_word_boundary("   commit"), 0);
+        assert_eq!(previous_word_boundary("   "), 0);
+        assert_eq!(previous_word_boundary("git\tcommit"), 3);
+        assert_eq!(previous_word_boundary("cd école"), 2);
+    }
+
+    #[test]
+    fn paste_with_newlines() {
+        let mut editor = LineEditor::new();
+        // A pasted "echo a\nb" followed by Enter, all events but the last are read in one burst
+        let mut events = "echo a\nb\n"
+            .chars()
+            .map(|c| match c {
+                '\n' => key(KeyCode::Enter),
+                c => key(KeyCode::Char(c)),
+            })
+            .collect::<Vec<_>>()
+            .into_iter();
+        let line = editor
+            .read_line_from(&mut Vec::new(), || {
+                let event = events.next().unwrap();
+                Ok(paste_enter(event, events.as_slice().first()))
+            })
+            .unwrap();
+
+        assert_eq!(line, Some("echo a b".to_string()));
+        assert_eq!(paste_enter(key(KeyCode::Enter), None), key(KeyCode::Enter));
+        let tab = key(KeyCode::Tab);
+        assert_eq!(paste_enter(key(KeyCode::Tab), Some(&tab)), tab);
+
+        // Only a key event following Enter is taken to be part of a paste
+        let resize = Event::Resize(80, 24);
+        assert_eq!(
+            paste_enter(key(KeyCode::Enter), Some(&resize)),
+            key(KeyCode::Enter)
+        );
+        assert_eq!(
+            paste_enter(key(KeyCode::Enter), Some(&tab)),
+            key(KeyCode::Char(' '))
+        );
+    }
+
+    #[test]
+    fn reverse_search() {
+        let entries = ["git status", "cargo test", "git stash", "ls"]
+            .iter()
+            .map(|entry| entry.to_string())
+            .collect::<Vec<_>>();
+        let mut search = ReverseSearch::new();
+        assert_eq!(search.matched(&entries), None);
+        assert_eq!(search.prompt(), "(reverse-i-search)`': ");
+
+        search.push('s', &entries);
+        assert_eq!(search.matched(&entries), Some("ls"));
+        search.push('t', &entries);
+        assert_eq!(search.matched(&entries), Some("git stash"));
+        search.older(&entries);
+        assert_eq!(search.matched(&entries), Some("cargo test"));
+        search.older(&entries);
+        assert_eq!(search.matched(&entries), Some("git status"));
+
+        // There are no older matches, so the match is kept
+        search.older(&entries);
+        assert_eq!(search.matched(&entries), Some("git status"));
+        assert!(search.failed);
+
+        search.push('x', &entries);
+        assert_eq!(search.matched(&entries), Some("git status"));
+        assert_eq!(search.prompt(), "(failed reverse-i-search)`stx': ");
+
+        search.pop(&entries);
+        assert_eq!(search.matched(&entries), Some("git stash"));
+        assert!(!search.failed);
+    }
+
+    #[test]
+    fn ctrl_r_searches_history() {
+        let mut editor = LineEditor::new();
+        editor.remember("cargo build".to_string());
+        editor.remember("git status".to_string());
+        editor.remember("cargo test".to_string());
+        let mut events = vec![
+            key(KeyCode::Char('l')),
+            ctrl('r'),
+            key(KeyCode::Char('c')),
+            key(KeyCode::Char('a')),
+            ctrl('r'),
+            key(KeyCode::Enter),
+            key(KeyCode::Char('!')),
+            ctrl('r'),
+            key(KeyCode::Char('g')),
+            key(KeyCode::Esc),
+            key(KeyCode::Enter),
+        ]
+        .into_iter();
+
+        let mut out = Vec::new();
+        let line = editor
+            .read_line_from(&mut out, || Ok(events.next().unwrap()))
+            .unwrap();
+        assert_eq!(line, Some("cargo build!".to_string()));
+
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("(reverse-i-search)`ca': "));
+    }
+
+    #[test]
+    fn ctrl_l_clears_screen() {
+        let mut editor = LineEditor::new();
+        let mut events = vec![
+            key(KeyCode::Char('l')),
+            key(KeyCode::Char('s')),
+            key(KeyCode::Left),
+            ctrl('l'),
+            key(KeyCode::Char('x')),
+            key(KeyCode::Enter),
+        ]
+        .into_iter();
+
+        let mut out = Vec::new();
+        let line = editor
+            .read_line_from(&mut out, || Ok(events.next().unwrap()))
+            .unwrap();
+        assert_eq!(line, Some("lxs".to_string()));
+
+        // The prompt and the line are drawn again after clearing the terminal
+        let out = String::from_utf8(out).unwrap();
+        let cleared = &out[out.find("\x1b[2J").unwrap()..];
+        assert!(cleared.contains("> "));
+        assert!(cleared.contains("ls"));
+    }
+
+    #[test]
+    fn ctrl_a_and_ctrl_e_move_cursor() {
+        let mut editor = LineEditor::new();
+        let events = vec![
+            key(KeyCode::Char('s')),
+            ctrl('a'),
+            key(KeyCode::Char('l')),
+            ctrl('e'),
+            key(KeyCode::Char('!')),
+            key(KeyCode::Home),
+            key(KeyCode::Char('$')),
+            key(KeyCode::Enter),
+        ];
+
+        assert_eq!(read_line(&mut editor, events), Some("$ls!".to_string()));
+
+        let mut line_buffer = line_buffer_at("cargo", 3);
+        line_buffer.move_to_start();
+        assert_eq!(line_buffer.cursor, 0);
+        assert_eq!(line_buffer.tail(), "cargo");
+    }
+
+    #[test]
+    fn ctrl_d_on_empty_line_ends_input() {
+        let mut editor = LineEditor::new();
+
+        assert_eq!(read_line(&mut editor, vec![ctrl('d')]), None);
+    }
+
+    #[test]
+    fn ctrl_d_on_non_empty_line_deletes() {
+        let mut editor = LineEditor::new();
+        let events = vec![key(KeyCode::Char('l')), ctrl('d'), key(KeyCode::Enter)];
+
+        assert_eq!(read_line(&mut editor, events), Some("l".to_string()));
+    }
+
+    #[test]
+    fn ctrl_c_cancels_line() {
+        let mut editor = LineEditor::new();
+        let events = vec![
+            key(KeyCode::Char('a')),
+            key(KeyCode::Char('b')),
+            ctrl('c'),
+            key(KeyCode::Char('l')),
+            key(KeyCode::Char('s')),
+            key(KeyCode::Enter),
+        ];
+
+        assert_eq!(read_line(&mut editor, events), Some("ls".to_string()));
+    }
+
+    #[test]
+    fn ctrl_c_exits() {
+        let mut editor = LineEditor::new();
+        editor.ctrl_c = CtrlC::Exit;
+        let events = vec![key(KeyCode::Char('a')), ctrl('c')];
+
+        assert_eq!(read_line(&mut editor, events), None);
+    }
+
+    #[test]
+    fn on_accept_reject_keeps_editing() {
+        let mut editor = LineEditor::new();
+        editor.on_accept = Some(Box::new(|line| {
+            if line.ends_with('|') {
+                AcceptDecision::Reject("Missing command after '|'".to_string())
+            } else {
+                AcceptDecision::Accept
+            }
+        }));
+        let mut events = vec![
+            key(KeyCode::Char('l')),
+            key(KeyCode::Char('s')),
+            key(KeyCode::Char('|')),
+            key(KeyCode::Enter),
+            key(KeyCode::Backspace),
+            key(KeyCode::Enter),
+        ]
+        .into_iter();
+
+        let mut out = Vec::new();
+        let line = editor
+            .read_line_from(&mut out, || Ok(events.next().unwrap()))
+            .unwrap();
+        assert_eq!(line, Some("ls".to_string()));
+
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("\r\nMissing command after '|'\r\n"));
+    }
+
+    #[test]
+    fn on_accept_replace() {
+        let mut editor = LineEditor::new();
+        editor.on_accept = Some(Box::new(|line| {
+            AcceptDecision::Replace(line.replace("ll", "ls -l"))
+        }));
+        let events = vec![
+            key(KeyCode::Char('l')),
+            key(KeyCode::Char('l')),
+            key(KeyCode::Enter),
+        ];
+
+        assert_eq!(read_line(&mut editor, events), Some("ls -l".to_string()));
+    }
+
+    #[test]
+    fn on_char_filters_characters() {
+        let mut editor = LineEditor::new();
+        editor.on_char = Some(Box::new(|c| c.is_ascii_digit()));
+        let events = vec![
+            key(KeyCode::Char('1')),
+            key(KeyCode::Char('a')),
+            key(KeyCode::Char('2')),
+            key(KeyCode::Char('\u{7}')),
+            key(KeyCode::Char('3')),
+            key(KeyCode::Enter),
+        ];
+
+        assert_eq!(read_line(&mut editor, events), Some("123".to_string()));
+    }
+
+    #[test]
+    fn autosuggestion_from_history() {
+        let mut editor = LineEditor::new();
+        editor.history.insert("git checkout main", ());
+        editor.history.insert("git commit", ());
+        editor.history.insert("cargo build", ());
+
+        assert_eq!(editor.autosuggestion("git c"), Some("ommit".to_string()));
+        assert_eq!(
+            editor.autosuggestion("git ch"),
+            Some("eckout main".to_string())
+        );
+        assert_eq!(editor.autosuggestion("git commit"), None);
+        assert_eq!(editor.autosuggestion("ls"), None);
+        assert_eq!(editor.autosuggestion(""), None);
+
+        // Using a line again makes it the most recent one
+        editor.history.insert("git checkout main", ());
+        assert_eq!(
+            editor.autosuggestion("git c"),
+            Some("heckout main".to_string())
+        );
+    }
+
+    #[test]
+    fn accept_autosuggestion() {
+        let mut editor = LineEditor::new();
+        editor.history.insert("cargo build --release", ());
+        editor.history.insert("cargo test", ());
+        let mut events = vec![
+            key(KeyCode::Char('c')),
+            key(KeyCode::Char('a')),
+            key(KeyCode::Char('r')),
+            key(KeyCode::Char('g')),
+            key(KeyCode::Char('o')),
+            key(KeyCode::Char(' ')),
+            key(KeyCode::Char('b')),
+            key(KeyCode::Right),
+            key(KeyCode::Enter),
+        ]
+        .into_iter();
+
+        let mut out = Vec::new();
+        let line = editor
+            .read_line_from(&mut out, || Ok(events.next().unwrap()))
+            .unwrap();
+        assert_eq!(line, Some("cargo build --release".to_string()));
+
+        // The suggestion is shown while typing
+        let out = String::from_utf8(out).unwrap();
+        let hint = Theme::default()
+            .autosuggestion
+            .apply("argo test")
+            .to_string();
+        assert!(out.contains(&hint));
+    }
+
+    #[test]
+    fn ignored_autosuggestion_is_not_committed() {
+        let mut editor = LineEditor::new();
+        editor.history.insert("cargo test", ());
+        let mut events = "cargo x"
+            .chars()
+            .map(|c| key(KeyCode::Char(c)))
+            .chain(vec![key(KeyCode::Backspace), key(KeyCode::Enter)])
+            .collect::<Vec<_>>()
+            .into_iter();
+
+        let mut out = Vec::new();
+        let line = editor
+            .read_line_from(&mut out, || Ok(events.next().unwrap()))
+            .unwrap();
+        assert_eq!(line, Some("cargo ".to_string()));
+
+        // The suggestion is shown again after erasing the character which diverged from it
+        let out = String::from_utf8(out).unwrap();
+        let hint = Theme::default().autosuggestion.apply("test").to_string();
+        assert_eq!(out.matches(&hint).count(), 2);
+    }
+
+    #[test]
+    fn end_accepts_autosuggestion() {
+        let mut editor = LineEditor::new();
+        editor.history.insert("cargo build --release", ());
+        let events = vec![
+            key(KeyCode::Char('c')),
+            key(KeyCode::End),
+            key(KeyCode::Char(' ')),
+            key(KeyCode::Char('-')),
+            key(KeyCode::Char('v')),
+            key(KeyCode::Enter),
+        ];
+
+        assert_eq!(
+            read_line(&mut editor, events),
+            Some("cargo build --release -v".to_string())
+        );
+    }
+
+    #[test]
+    fn end_without_autosuggestion() {
+        let mut editor = LineEditor::new();
+        editor.history.insert("cargo build", ());
+        let events = vec![
+            key(KeyCode::Char('l')),
+            key(KeyCode::End),
+            key(KeyCode::Char('s')),
+            key(KeyCode::Enter),
+        ];
+        assert_eq!(read_line(&mut editor, events), Some("ls".to_string()));
+
+        // Away from the end of the line, End moves the cursor
+        let mut line_buffer = line_buffer_at("cargo", 2);
+        line_buffer.move_to_end();
+        line_buffer.insert('!');
+        assert_eq!(line_buffer.as_str(), "cargo!");
+    }
+
+    #[test]
+    fn hidden_line_is_not_drawn() {
+        let mut editor = LineEditor::new();
+        editor.history.insert("xylophone", ());
+        editor.hidden = true;
+        let mut events = vec![
+            key(KeyCode::Char('x')),
+            key(KeyCode::Char('y')),
+            key(KeyCode::Char('q')),
+            key(KeyCode::Backspace),
+            key(KeyCode::Tab),
+            Event::Resize(40, 10),
+            key(KeyCode::Char('z')),
+            key(KeyCode::Enter),
+        ]
+        .into_iter();
+
+        let mut out = Vec::new();
+        let line = editor
+            .read_line_from(&mut out, || Ok(events.next().unwrap()))
+            .unwrap();
+        assert_eq!(line, Some("xyz".to_string()));
+
+        // Neither the line, nor completions or suggestions from the history are drawn
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("> "));
+        assert!(!out.contains(|c| "xyzqlophne".contains(c)));
+    }
+
+    #[test]
+    fn edit_line_gives_up() {
+        let mut editor = LineEditor::new();
+        let mut events = vec![Some(key(KeyCode::Char('l'))), None].into_iter();
+
+        let line = editor
+            .edit_line(&mut Vec::new(), || Ok(events.next().unwrap()))
+            .unwrap();
+        assert_eq!(line, None);
+    }
+
+    #[test]
+    fn kill_and_yank() {
+        let mut editor = LineEditor::new();
+        let mut events = "hello world"
+            .chars()
+            .map(|c| key(KeyCode::Char(c)))
+            .collect::<Vec<_>>();
+        events.extend(vec![
+            ctrl('w'),
+            ctrl('u'),
+            ctrl('y'),
+            // Cycle to the kill before the yanked one, and wrap around to the most recent one
+            alt('y'),
+            alt('y'),
+            ctrl('y'),
+            alt('y'),
+            key(KeyCode::Enter),
+        ]);
+
+        assert_eq!(
+            read_line(&mut editor, events),
+            Some("hello world".to_string())
+        );
+        assert_eq!(editor.kill_ring, vec!["world", "hello "]);
+    }
+
+    #[test]
+    fn kill_ring_is_bounded() {
+        let mut editor = LineEditor::new();
+        for i in 0..=KILL_RING_SIZE {
+            editor.kill(i.to_string());
+        }
+        editor.kill(String::new());
+
+        assert_eq!(editor.kill_ring.len(), KILL_RING_SIZE);
+        assert_eq!(editor.kill_ring[0], "1");
+        assert_eq!(
+            editor.kill_ring.last().unwrap(),
+            &KILL_RING_SIZE.to_string()
+        );
+    }
+
+    #[test]
+    fn line_buffer_kill_and_yank() {
+        let mut line_buffer = line_buffer_at("cargo  build --release", 12);
+        assert_eq!(line_buffer.word_start(), 7);
+
+        let killed = line_buffer.remove(line_buffer.word_start()..line_buffer.cursor);
+        assert_eq!(killed, "build");
+        assert_eq!(line_buffer.as_str(), "cargo   --release");
+        assert_eq!(line_buffer.cursor, 7);
+
+        line_buffer.insert_str(&killed);
+        assert_eq!(line_buffer.as_str(), "cargo  build --release");
+        assert_eq!(line_buffer.cursor, 12);
+    }
+
+    #[test]
+    fn known_prefix() {
+        let mut history = Trie::new();
+        history.insert("git status", ());
+        history.insert("ls -la", ());
+
+        assert!(is_known_prefix(&history, "git"));
+        assert!(is_known_prefix(&history, "git status"));
+        assert!(is_known_prefix(&history, "l"));
+        assert!(!is_known_prefix(&history, "git status -s"));
+        assert!(!is_known_prefix(&history, "gti"));
+    }
+
+    #[test]
+    fn highlight_prefix() {
+        let mut editor = LineEditor::new();
+        editor.history.insert("ls -la", ());
+        editor.highlight_prefix = true;
+        let mut events = vec![
+            key(KeyCode::Char('l')),
+            key(KeyCode::Char('x')),
+            key(KeyCode::Backspace),
+            key(KeyCode::Enter),
+        ]
+        .into_iter();
+
+        let mut out = Vec::new();
+        editor
+            .read_line_from(&mut out, || Ok(events.next().unwrap()))
+            .unwrap();
+
+        // The line turns red after typing 'x', and green again after removing it
+        let theme = Theme::default();
+        let out = String::from_utf8(out).unwrap();
+        let red = out.find(&theme.unknown_prefix.apply("lx").to_string());
+        let green = out.rfind(&theme.known_prefix.apply("l").to_string());
+        assert!(red.is_some());
+        assert!(red < green);
+    }
+
+    #[test]
+    fn vi_mode_transitions() {
+        let mut editor = LineEditor::new();
+        editor.keymap = Keymap::Vi;
+        let events = vec![
+            key(KeyCode::Char('l')),
+            key(KeyCode::Char('s')),
+            // 'x' deletes the 's', and 'h' moves onto the 'l'
+            key(KeyCode::Esc),
+            key(KeyCode::Char('x')),
+            key(KeyCode::Char('h')),
+            // Insert before the 'l', and append after it
+            key(KeyCode::Char('i')),
+            key(KeyCode::Char('c')),
+            key(KeyCode::Esc),
+            key(KeyCode::Char('$')),
+            key(KeyCode::Char('a')),
+            key(KeyCode::Char('s')),
+            key(KeyCode::Enter),
+        ];
+
+        assert_eq!(read_line(&mut editor, events), Some("cls".to_string()));
+        assert_eq!(editor.mode, EditMode::Insert);
+
+        // Escape in insert mode, and normal mode commands, are ignored by the emacs keymap
+        editor.keymap = Keymap::Emacs;
+        let events = vec![
+            key(KeyCode::Char('l')),
+            key(KeyCode::Esc),
+            key(KeyCode::Char('x')),
+            key(KeyCode::Enter),
+        ];
+        assert_eq!(read_line(&mut editor, events), Some("lx".to_string()));
+    }
+
+    #[test]
+    fn switch_keymap() {
+        let mut editor = LineEditor::new();
+        let events = || {
+            vec![
+                key(KeyCode::Char('a')),
+                key(KeyCode::Char('b')),
+                key(KeyCode::Esc),
+                key(KeyCode::Char('0')),
+                key(KeyCode::Char('x')),
+                key(KeyCode::Enter),
+            ]
+        };
+
+        editor.set_keymap(Keymap::Vi);
+        assert_eq!(read_line(&mut editor, events()), Some("b".to_string()));
+        assert_eq!(editor.mode, EditMode::Normal);
+
+        // Switching back starts in insert mode, and typed characters are inserted again
+        editor.set_keymap(Keymap::Emacs);
+        assert_eq!(editor.mode, EditMode::Insert);
+        assert_eq!(read_line(&mut editor, events()), Some("ab0x".to_string()));
+    }
+
+    #[test]
+    fn vi_motions() {
+        let mut editor = LineEditor::new();
+        let mut line_buffer = line_buffer_at("git  commit -m", 1);
+
+        editor.vi_command('w', &mut line_buffer);
+        assert_eq!(line_buffer.cursor, 5);
+        editor.vi_command('w', &mut line_buffer);
+        assert_eq!(line_buffer.cursor, 12);
+        editor.vi_command('w', &mut line_buffer);
+        assert_eq!(line_buffer.cursor, 14);
+
+        editor.vi_command('b', &mut line_buffer);
+        assert_eq!(line_buffer.cursor, 12);
+        editor.vi_command('b', &mut line_buffer);
+        assert_eq!(line_buffer.cursor, 5);
+        editor.vi_command('h', &mut line_buffer);
+        assert_eq!(line_buffer.cursor, 4);
+        editor.vi_command('l', &mut line_buffer);
+        assert_eq!(line_buffer.cursor, 5);
+
+        editor.vi_command('0', &mut line_buffer);
+        assert_eq!(line_buffer.cursor, 0);
+        editor.vi_command('h', &mut line_buffer);
+        assert_eq!(line_buffer.cursor, 0);
+        editor.vi_command('$', &mut line_buffer);
+        assert_eq!(line_buffer.cursor, 14);
+        editor.vi_command('l', &mut line_buffer);
+        assert_eq!(line_buffer.cursor, 14);
+    }
+
+    #[test]
+    fn resize_redraws_line() {
+        let mut editor = LineEditor::new();
+        let mut events = vec![
+            key(KeyCode::Char('l')),
+            key(KeyCode::Char('s')),
+            Event::Resize(40, 10),
+            key(KeyCode::Enter),
+        ]
+        .into_iter();
+
+        let mut out = Vec::new();
+        let line = editor
+            .read_line_from(&mut out, || Ok(events.next().unwrap()))
+            .unwrap();
+        assert_eq!(line, Some("ls".to_string()));
+
+        // The line is printed once while typing, and once more after the resize
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.matches("> ").count(), 2);
+    }
+
+    #[test]
+    fn screen_position_wraps() {
+        let screen = Screen::new(10, "> ");
+
+        assert_eq!(screen.position(""), (0, 2));
+        assert_eq!(screen.position("abcdefg"), (0, 9));
+        assert_eq!(screen.position("abcdefgh"), (1, 0));
+        assert_eq!(screen.position("abcdefghijklmnopqrstuvw"), (2, 5));
+    }
+
+    #[test]
+    fn screen_position_wide_prompt() {
+        // The crab takes two columns, and the space one more
+        let screen = Screen::new(10, "🦀 ");
+
+        assert_eq!(screen.position(""), (0, 3));
+        assert_eq!(screen.position("abcdef"), (0, 9));
+        assert_eq!(screen.position("abcdefg"), (1, 0));
+        assert_eq!(screen.index_at(&line_buffer("abc"), 0, 4), Some(1));
+
+        // A prompt wider than the terminal wraps as well
+        let screen = Screen::new(4, "漢字漢> ");
+        assert_eq!(screen.position(""), (2, 0));
+    }
+
+    #[test]
+    fn screen_tracks_row() {
+        let mut screen = Screen::new(10, "> ");
+        let mut line_buffer = LineBuffer::new();
+        let mut out = Vec::new();
+
+        for c in "abcdefghijk".chars() {
+            line_buffer.insert(c);
+            screen.insert(&mut out, &line_buffer, c).unwrap();
+        }
+        assert_eq!(screen.row, 1);
+
+        // Moving back to the start of the line moves the cursor up one row
+        line_buffer.cursor = 0;
+        screen.redraw(&mut out, &line_buffer).unwrap();
+        assert_eq!(screen.row, 0);
+    }
+
+    #[test]
+    fn screen_index_at() {
+        let screen = Screen::new(10, "> ");
+        let line_buffer = line_buffer("abcdefghijklmn");
+
+        assert_eq!(screen.index_at(&line_buffer, 0, 0), Some(0));
+        assert_eq!(screen.index_at(&line_buffer, 0, 2), Some(0));
+        assert_eq!(screen.index_at(&line_buffer, 0, 5), Some(3));
+        assert_eq!(screen.index_at(&line_buffer, 1, 0), Some(8));
+        assert_eq!(screen.index_at(&line_buffer, 1, 9), Some(14));
+        assert_eq!(screen.index_at(&line_buffer, -1, 5), None);
+        assert_eq!(screen.index_at(&line_buffer, 2, 0), None);
+    }
+
+    #[test]
+    fn screen_index_at_multibyte() {
+        let screen = Screen::new(10, "> ");
+        let line_buffer = line_buffer("héllo");
+
+        assert_eq!(screen.index_at(&line_buffer, 0, 4), Some(3));
+    }
+
+    #[test]
+    fn display_width() {
+        assert_eq!(str_width("abc"), 3);
+        assert_eq!(str_width("漢字"), 4);
+        assert_eq!(str_width("e\u{301}"), 1);
+        assert_eq!(str_width("🦀"), 2);
+    }
+
+    #[test]
+    fn screen_position_mixed_widths() {
+        let screen = Screen::new(10, "> ");
+
+        // ASCII, a CJK character, and a combining mark
+        assert_eq!(screen.position("a"), (0, 3));
+        assert_eq!(screen.position("a漢"), (0, 5));
+        assert_eq!(screen.position("a漢e\u{301}"), (0, 6));
+
+        // A wide character which doesn't fit on the row is drawn on the next row
+        assert_eq!(screen.position("abcdefg漢"), (1, 2));
+        assert_eq!(screen.index_at(&line_buffer("abcdefg漢b"), 1, 1), Some(7));
+        assert_eq!(screen.index_at(&line_buffer("abcdefg漢b"), 1, 2), Some(10));
+    }
+
+    fn line_buffer(text: &str) -> LineBuffer {
+        let mut line_buffer = LineBuffer::new();
+        for c in text.chars() {
+            line_buffer.insert(c);
+        }
+        line_buffer
+    }
+
+    #[test]
+    fn backspace_wide_character() {
+        let mut screen = Screen::new(10, "> ");
+        let mut line_buffer = line_buffer("a");
+        let mut out = Vec::new();
+
+        line_buffer.insert('漢');
+        screen.insert(&mut out, &line_buffer, '漢').unwrap();
+
+        out.clear();
+        let removed = line_buffer.backspace().unwrap();
+        screen.backspace(&mut out, &line_buffer, &removed).unwrap();
+
+        assert_eq!(removed, "漢");
+        assert_eq!(line_buffer.as_str(), "a");
+        assert_eq!(String::from_utf8(out).unwrap(), "\u{1b}[2D\u{1b}[K");
+    }
+
+    #[test]
+    fn combining_character_is_one_grapheme() {
+        let mut screen = Screen::new(10, "> ");
+        let mut line_buffer = line_buffer("ae\u{301}");
+        assert_eq!(screen.position(line_buffer.as_str()), (0, 4));
+
+        // Backspace removes the base letter together with its accent
+        let mut out = Vec::new();
+        let removed = line_buffer.backspace().unwrap();
+        screen.row = 0;
+        screen.backspace(&mut out, &line_buffer, &removed).unwrap();
+        assert_eq!(removed, "e\u{301}");
+        assert_eq!(line_buffer.as_str(), "a");
+        assert_eq!(String::from_utf8(out).unwrap(), "\u{1b}[1D\u{1b}[K");
+
+        // So does Ctrl+D
+        let mut line_buffer = line_buffer_at("e\u{301}b", 0);
+        assert_eq!(line_buffer.delete(), Some("e\u{301}".to_string()));
+        assert_eq!(line_buffer.as_str(), "b");
+    }
+
+    #[test]
+    fn zero_width_joiner_is_one_grapheme() {
+        let mut line_buffer = line_buffer("a👩\u{200D}💻");
+
+        assert_eq!(line_buffer.backspace(), Some("👩\u{200D}💻".to_string()));
+        assert_eq!(line_buffer.as_str(), "a");
+    }
+
+    fn line_buffer_at(text: &str, cursor: usize) -> LineBuffer {
+        let mut line_buffer = line_buffer(text);
+        line_buffer.cursor = cursor;
+        line_buffer
+    }
+
+    #[test]
+    fn line_buffer_delete() {
+        let mut line_buffer = line_buffer("ab");
+
+        // The cursor is at the end of the line
+        assert_eq!(line_buffer.delete(), None);
+
+        line_buffer.cursor = 0;
+        assert_eq!(line_buffer.delete(), Some("a".to_string()));
+        assert_eq!(line_buffer.as_str(), "b");
+        assert_eq!(line_buffer.delete(), Some("b".to_string()));
+        assert_eq!(line_buffer.delete(), None);
+        assert!(line_buffer.is_empty());
+    }
+
+    #[test]
+    fn line_buffer_transpose() {
+        // In the middle of the line, the characters around the cursor are swapped
+        let mut middle = line_buffer_at("teh cat", 2);
+        assert!(middle.transpose());
+        assert_eq!(middle.as_str(), "the cat");
+        assert_eq!(middle.cursor, 3);
+
+        // At the end of the line, the last two characters are swapped
+        let mut end = line_buffer("ls -al漢e\u{301}");
+        assert!(end.transpose());
+        assert_eq!(end.as_str(), "ls -ale\u{301}漢");
+        assert_eq!(end.cursor, end.as_str().len());
+
+        // At the start of the line, or with fewer than two characters, nothing is swapped
+        let mut start = line_buffer_at("ab", 0);
+        assert!(!start.transpose());
+        assert_eq!(start.as_str(), "ab");
+        assert!(!line_buffer("a").transpose());
+    }
+
+    #[test]
+    fn line_buffer_change_word() {
+        let mut upper = line_buffer_at("git  status --short", 3);
+        upper.change_word(str::to_uppercase);
+        assert_eq!(upper.as_str(), "git  STATUS --short");
+        assert_eq!(upper.cursor, 11);
+
+        // Uppercasing 'ß' yields two characters
+        let mut upper = line_buffer_at("straße", 0);
+        upper.change_word(str::to_uppercase);
+        assert_eq!(upper.as_str(), "STRASSE");
+        assert_eq!(upper.cursor, upper.as_str().len());
+
+        let mut lower = line_buffer_at("ÉCOLE Normale", 0);
+        lower.change_word(str::to_lowercase);
+        assert_eq!(lower.as_str(), "école Normale");
+        assert_eq!(lower.cursor, "école".len());
+
+        let mut capitalized = line_buffer_at("cd ÉCOLE", 2);
+        capitalized.change_word(capitalize);
+        assert_eq!(capitalized.as_str(), "cd École");
+        assert_eq!(capitalized.cursor, capitalized.as_str().len());
+    }
+
+    #[test]
+    fn line_buffer_insert_paired() {
+        let mut line_buffer = line_buffer("echo ");
+        for c in "$(ls [a-z]".chars() {
+            line_buffer.insert_paired(c);
+        }
+        assert_eq!(line_buffer.as_str(), "echo $(ls [a-z])");
+        assert_eq!(line_buffer.tail(), ")");
+
+        // Typing the closing character skips over it
+        assert!(!line_buffer.insert_paired(')'));
+        assert_eq!(line_buffer.as_str(), "echo $(ls [a-z])");
+        assert!(line_buffer.tail().is_empty());
+
+        // Unless it isn't under the cursor
+        assert!(line_buffer.insert_paired(')'));
+        assert_eq!(line_buffer.as_str(), "echo $(ls [a-z]))");
+
+        let mut quotes = LineBuffer::new();
+        for c in "'don't'".chars() {
+            quotes.insert_paired(c);
+        }
+        assert_eq!(quotes.as_str(), "'don't'");
+        assert!(quotes.tail().is_empty());
+    }
+
+    #[test]
+    fn layout_columns_fits_width() {
+        let items = ["a", "bb", "ccc", "dddd", "e"]
+            .iter()
+            .map(|item| item.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            render(layout_columns(&items, 80)),
+            vec!["a  bb  ccc  dddd  e"]
+        );
+        assert_eq!(
+            render(layout_columns(&items, 11)),
+            vec!["a   ccc   e", "bb  dddd"]
+        );
+        assert_eq!(
+            render(layout_columns(&items, 10)),
+            vec!["a    dddd", "bb   e", "ccc"]
+        );
+        assert_eq!(
+            render(layout_columns(&items, 4)),
+            vec!["a", "bb", "ccc", "dddd", "e"]
+        );
+    }
+
+    #[test]
+    fn layout_columns_item_wider_than_width() {
+        let items = vec!["git status".to_string(), "ls".to_string()];
+
+        assert_eq!(render(layout_columns(&items, 5)), vec!["git status", "ls"]);
+        assert!(render(layout_columns(&[], 5)).is_empty());
+    }
+
+    #[test]
+    fn completions_from_commands() {
+        let mut editor = LineEditor::with_commands(Box::new(Commands));
+        editor.history.insert("status", ());
+        editor.history.insert("grep -r TODO", ());
+
+        // The first word is completed from the commands, the other words from the history
+        assert_eq!(editor.completions("g", 0..1), vec!["git", "grep"]);
+        assert_eq!(editor.completions("  g status", 2..3), vec!["git", "grep"]);
+        assert!(editor.completions("cargo", 0..5).is_empty());
+        assert_eq!(editor.completions("git st", 4..6), vec!["status"]);
+        assert_eq!(editor.completions("git g", 4..5), vec!["grep -r TODO"]);
+    }
+
+    #[test]
+    fn expansions() {
+        let mut editor = LineEditor::with_commands(Box::new(Commands));
+        editor.history.insert("status", ());
+        editor.history.insert("stash", ());
+        editor.history.insert("stash pop", ());
+
+        let expansion = |line: &str, word: Range<usize>| {
+            let completions = editor.completions(line, word.clone());
+            editor.expansion(line, word, &completions)
+        };
+        // A single completion is completed fully, otherwise up to where the completions diverge
+        assert_eq!(expansion("l", 0..1), Some("ls".to_string()));
+        assert_eq!(expansion("git s", 4..5), Some("sta".to_string()));
+        assert_eq!(expansion("git stas", 4..8), Some("stash".to_string()));
+        assert_eq!(expansion("git stat", 4..8), Some("status".to_string()));
+        // Nothing to add, so the completions are listed
+        assert_eq!(expansion("g", 0..1), None);
+        assert_eq!(expansion("git sta", 4..7), None);
+        assert_eq!(expansion("git x", 4..5), None);
+    }
+
+    #[test]
+    fn tab_expands_common_prefix() {
+        let mut editor = LineEditor::new();
+        editor.history.insert("commit", ());
+        editor.history.insert("config", ());
+
+        // The first Tab completes the shared prefix, the second lists the completions, and the
+        // third picks the first one
+        let events = "git c"
+            .chars()
+            .map(|c| key(KeyCode::Char(c)))
+            .chain(vec![
+                key(KeyCode::Tab),
+                key(KeyCode::Tab),
+                key(KeyCode::Tab),
+                key(KeyCode::Enter),
+            ])
+            .collect();
+        assert_eq!(
+            read_line(&mut editor, events),
+            Some("git commit".to_string())
+        );
+    }
+
+    #[test]
+    fn current_words() {
+        assert_eq!(current_word("", 0), 0..0);
+        assert_eq!(current_word("git", 3), 0..3);
+        assert_eq!(current_word("git comm", 8), 4..8);
+        assert_eq!(current_word("git comm", 6), 4..8);
+        assert_eq!(current_word("git comm", 4), 4..8);
+        assert_eq!(current_word("git comm", 3), 0..3);
+        assert_eq!(current_word("git ", 4), 4..4);
+        assert_eq!(current_word("git  --amend", 4), 4..4);
+        assert_eq!(current_word("git\tst --short", 6), 4..6);
+        assert_eq!(current_word("cd écol", 7), 3..8);
+    }
+
+    #[test]
+    fn tab_completes_current_word() {
+        let mut editor = LineEditor::with_commands(Box::new(Commands));
+        editor.history.insert("commit", ());
+        editor.history.insert("status", ());
+
+        let events = vec![
+            key(KeyCode::Char('g')),
+            key(KeyCode::Char('i')),
+            key(KeyCode::Char('t')),
+            key(KeyCode::Char(' ')),
+            key(KeyCode::Char('c')),
+            key(KeyCode::Char('o')),
+            key(KeyCode::Tab),
+            key(KeyCode::Enter),
+        ];
+        assert_eq!(
+            read_line(&mut editor, events),
+            Some("git commit".to_string())
+        );
+
+        // The rest of the line after the word under the cursor is kept
+        let events = "git st --short"
+            .chars()
+            .map(|c| key(KeyCode::Char(c)))
+            .chain(iter::repeat_with(|| key(KeyCode::Left)).take(8))
+            .chain(vec![
+                key(KeyCode::Tab),
+                key(KeyCode::Char('!')),
+                key(KeyCode::Enter),
+            ])
+            .collect();
+        assert_eq!(
+            read_line(&mut editor, events),
+            Some("git status! --short".to_string())
+        );
+    }
+
+    #[test]
+    fn completion_cycle() {
+        let mut cycle = CompletionCycle::new("g", 4, vec!["git".to_string(), "grep".to_string()]);
+        assert_eq!(cycle.range(), 4..5);
+        assert_eq!(cycle.forward(), "git");
+        assert_eq!(cycle.range(), 4..7);
+        assert_eq!(cycle.forward(), "grep");
+        // Wrap around to the typed line, in both directions
+        assert_eq!(cycle.forward(), "g");
+        assert_eq!(cycle.forward(), "git");
+        assert_eq!(cycle.backward(), "g");
+        assert_eq!(cycle.backward(), "grep");
+
+        let mut empty = CompletionCycle::new("x", 0, Vec::new());
+        assert_eq!(empty.forward(), "x");
+        assert_eq!(empty.backward(), "x");
+    }
+
+    #[test]
+    fn tab_cycles_through_completions() {
+        let mut editor = LineEditor::with_commands(Box::new(Commands));
+        let events = vec![
+            key(KeyCode::Char('g')),
+            // The first Tab lists the completions, the next ones cycle through them
+            key(KeyCode::Tab),
+            key(KeyCode::Tab),
+            key(KeyCode::Tab),
+            Event::Key(KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT)),
+            key(KeyCode::Char(' ')),
+            // Typing ends the cycle, so Tab lists the completions of the new line
+            key(KeyCode::Tab),
+            key(KeyCode::Enter),
+        ];
+
+        assert_eq!(read_line(&mut editor, events), Some("git ".to_string()));
+    }
+
+    #[test]
+    fn custom_theme() {
+        let mut editor = LineEditor::with_commands(Box::new(Commands));
+        let theme = Theme {
+            prompt: ContentStyle::new().foreground(Color::Magenta),
+            suggestion: ContentStyle::new().foreground(Color::Blue),
+            matched: ContentStyle::new().foreground(Color::Red),
+            autosuggestion: ContentStyle::new().foreground(Color::Green),
+            ..Theme::default()
+        };
+        editor.theme = theme;
+        let mut events = vec![
+            key(KeyCode::Char('g')),
+            key(KeyCode::Tab),
+            key(KeyCode::Enter),
+        ]
+        .into_iter();
+
+        let mut out = Vec::new();
+        editor
+            .read_line_from(&mut out, || Ok(events.next().unwrap()))
+            .unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(&theme.prompt.apply("> ").to_string()));
+        assert!(out.contains(&theme.matched.apply('g').to_string()));
+        assert!(out.contains(&theme.suggestion.apply('r').to_string()));
+        assert!(!out.contains(&Theme::default().prompt.apply("> ").to_string()));
+    }
+
+    #[test]
+    fn configured_prompt() {
+        assert_eq!(
+            color_arg("--prompt-color=blue", "--prompt-color="),
+            Some(Color::Blue)
+        );
+        assert_eq!(
+            color_arg("--prompt-color=Dark_Cyan", "--prompt-color="),
+            Some(Color::DarkCyan)
+        );
+        assert_eq!(color_arg("--prompt-color=teal", "--prompt-color="), None);
+        assert_eq!(color_arg("--completion-color=red", "--prompt-color="), None);
+
+        let style = ContentStyle::new().foreground(Color::Blue);
+        let mut out = Vec::new();
+        print_prompt(&mut out, "treeline> ", style).unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("treeline> "));
+        assert_eq!(out, style.apply("treeline> ").to_string());
+    }
 }
@@ -0,0 +1,339 @@
+//! This module provides the [`TrieBuilder`], used to create a configured [`Trie`].
+
+use crate::{cache::ContainsCache, Clock, Normalizer, Trie};
+use std::{fmt, sync::Arc, time::SystemTime};
+
+/// Builder for a [`Trie`] with non-default options.
+///
+/// # Examples
+///
+/// ```
+/// use treeline::TrieBuilder;
+///
+/// let mut trie = TrieBuilder::new()
+///     .normalizer(|word| Some(word.trim().to_string()))
+///     .max_word_len(5)
+///     .build();
+///
+/// assert!(trie.insert(" Hello ", ()));
+/// assert!(!trie.insert("Hello world", ()));
+/// ```
+#[derive(Default)]
+pub struct TrieBuilder {
+    /// Function applied to every word before it is inserted.
+    normalizer: Option<Normalizer>,
+    /// The maximum length of a word, in characters.
+    max_word_len: Option<usize>,
+    /// The maximum number of words in the trie.
+    capacity: Option<usize>,
+    /// The clock used to timestamp insertions.
+    clock: Option<Clock>,
+    /// Whether words are matched regardless of their case.
+    case_insensitive: bool,
+    /// Whether letters followed by a combining accent are composed.
+    compose_accents: bool,
+    /// The number of words the Bloom filter consulted by [`Trie::contains`] is sized for.
+    contains_cache: Option<usize>,
+}
+
+impl TrieBuilder {
+    /// Create a builder with the default options, i.e. building a trie equal to [`Trie::new`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the function which is applied to every word before it is inserted, see
+    /// [`Trie::with_normalizer`].
+    pub fn normalizer<F>(mut self, normalizer: F) -> Self
+    where
+        F: Fn(&str) -> Option<String> + Send + Sync + 'static,
+    {
+        self.normalizer = Some(Arc::new(normalizer));
+        self
+    }
+
+    /// Sets the maximum length of a word, in characters. Longer words are rejected on insert.
+    ///
+    /// The length is checked after the word has been passed through the
+    /// [`normalizer`](TrieBuilder::normalizer).
+    pub fn max_word_len(mut self, max_word_len: usize) -> Self {
+        self.max_word_len = Some(max_word_len);
+        self
+    }
+
+    /// Sets the maximum number of words in the trie, see [`Trie::with_lru_capacity`].
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    /// Sets the clock used to timestamp insertions, see [`Trie::with_clock`].
+    pub fn clock<F>(mut self, clock: F) -> Self
+    where
+        F: Fn() -> SystemTime + Send + Sync + 'static,
+    {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
+    /// Sets whether words are matched regardless of their case, see
+    /// [`Trie::new_case_insensitive`].
+    pub fn case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.case_insensitive = case_insensitive;
+        self
+    }
+
+    /// Sets whether a letter followed by a combining accent matches the precomposed letter, e.g.
+    /// "e" followed by U+0301 matches "é".
+    ///
+    /// The trie is keyed by characters, so by default the two forms of such a letter are different
+    /// words. With this option, words are stored with their accents composed, and every lookup
+    /// composes the accents of the given word too, like a
+    /// [case-insensitive](TrieBuilder::case_insensitive) lookup folds its case. This is disabled by
+    /// default.
+    ///
+    /// The composition approximates the Unicode Normalization Form C for the accented letters of
+    /// the Latin-1 Supplement and Latin Extended-A blocks, other combining characters are kept as
+    /// is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use treeline::TrieBuilder;
+    ///
+    /// let mut trie = TrieBuilder::new().compose_accents(true).build();
+    /// trie.insert("cafe\u{301}", ());
+    ///
+    /// assert!(trie.contains("café"));
+    /// assert_eq!(trie.words().collect::<Vec<_>>(), vec!["café"]);
+    /// ```
+    pub fn compose_accents(mut self, compose_accents: bool) -> Self {
+        self.compose_accents = compose_accents;
+        self
+    }
+
+    /// Enables a Bloom filter of the inserted words, sized for `expected_words` words, which
+    /// [`Trie::contains`] consults before descending into the trie.
+    ///
+    /// This speeds up lookups which mostly miss, as about 99% of the words which were never
+    /// inserted are rejected without visiting any node. A word which was inserted is never
+    /// rejected. The filter is cleared by [`Trie::clear`], but deleting a word doesn't remove it
+    /// from the filter, so looking it up descends into the trie again. Inserting more than
+    /// `expected_words` words only lets more absent words through. The filter is disabled by
+    /// default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use treeline::TrieBuilder;
+    ///
+    /// let mut trie = TrieBuilder::new().contains_cache(1000).build();
+    /// trie.insert("cargo build", ());
+    ///
+    /// assert!(trie.contains("cargo build"));
+    /// assert!(!trie.contains("cargo test"));
+    /// ```
+    pub fn contains_cache(mut self, expected_words: usize) -> Self {
+        self.contains_cache = Some(expected_words);
+        self
+    }
+
+    /// Creates the [`Trie`] with the configured options.
+    pub fn build<V>(self) -> Trie<V> {
+        let normalizer = match (self.normalizer, self.max_word_len) {
+            (normalizer, None) => normalizer,
+            (normalizer, Some(max_word_len)) => {
+                let normalizer: Normalizer = Arc::new(move |word: &str| {
+                    let word = match &normalizer {
+                        Some(normalize) => normalize(word)?,
+                        None => word.to_string(),
+                    };
+
+                    if word.chars().count() > max_word_len {
+                        None
+                    } else {
+                        Some(word)
+                    }
+                });
+                Some(normalizer)
+            }
+        };
+
+        let trie = Trie::new();
+        Trie {
+            normalizer,
+            capacity: self.capacity,
+            clock: self.clock.unwrap_or(trie.clock),
+            case_insensitive: self.case_insensitive,
+            compose_accents: self.compose_accents,
+            contains_cache: self.contains_cache.map(ContainsCache::new),
+            ..trie
+        }
+    }
+}
+
+impl fmt::Debug for TrieBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrieBuilder")
+            .field("normalizer", &self.normalizer.as_ref().map(|_| ".."))
+            .field("max_word_len", &self.max_word_len)
+            .field("capacity", &self.capacity)
+            .field("clock", &self.clock.as_ref().map(|_| ".."))
+            .field("case_insensitive", &self.case_insensitive)
+            .field("compose_accents", &self.compose_accents)
+            .field("contains_cache", &self.contains_cache)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TrieBuilder;
+
+    #[test]
+    fn default_options() {
+        let mut trie = TrieBuilder::new().build();
+
+        assert!(trie.insert(" Hello world! ", ()));
+        assert!(trie.find(" Hello world! ").is_some());
+    }
+
+    #[test]
+    fn max_word_len() {
+        let mut trie = TrieBuilder::new().max_word_len(5).build();
+
+        assert!(trie.insert("Hello", ()));
+        assert!(!trie.insert("Hello!", ()));
+        assert!(trie.find("Hello").is_some());
+        assert!(trie.find("Hello!").is_none());
+    }
+
+    #[test]
+    fn capacity() {
+        let mut trie = TrieBuilder::new().capacity(1).build();
+
+        trie.insert("Hello world!", ());
+        trie.insert("Goedemorgen", ());
+        assert!(trie.find("Hello world!").is_none());
+        assert!(trie.find("Goedemorgen").is_some());
+    }
+
+    #[test]
+    fn max_word_len_after_normalizer() {
+        let mut trie = TrieBuilder::new()
+            .normalizer(|word| Some(word.trim().to_string()))
+            .max_word_len(5)
+            .build();
+
+        assert!(trie.insert("  Hello\n", ()));
+        assert!(trie.find("Hello").is_some());
+    }
+
+    #[test]
+    fn case_insensitive() {
+        let mut trie = TrieBuilder::new()
+            .normalizer(|word| Some(word.trim().to_string()))
+            .case_insensitive(true)
+            .build();
+
+        assert!(trie.insert(" Hello ", ()));
+        assert!(trie.contains("hELLO"));
+        assert_eq!(trie.words().collect::<Vec<_>>(), vec!["Hello"]);
+    }
+
+    #[test]
+    fn compose_accents() {
+        let decomposed = "cafe\u{301} cre\u{300}me";
+        let precomposed = "café crème";
+
+        let mut trie = TrieBuilder::new().compose_accents(true).build();
+        trie.insert(decomposed, ());
+        assert!(trie.contains(precomposed));
+        assert!(trie.contains(decomposed));
+        assert_eq!(trie.words().collect::<Vec<_>>(), vec![precomposed]);
+        assert_eq!(trie.words_with_prefix("cafe\u{301}").count(), 1);
+        assert!(trie.delete(precomposed));
+        assert!(trie.is_empty());
+
+        trie.insert(precomposed, ());
+        assert!(trie.contains(decomposed));
+        assert_eq!(trie.words_with_prefix("cafe\u{301}").count(), 1);
+        assert!(trie.delete(decomposed));
+        assert!(trie.is_empty());
+
+        // By default the forms are different words
+        let mut trie = TrieBuilder::new().build();
+        trie.insert(decomposed, ());
+        assert!(!trie.contains(precomposed));
+        assert!(trie.contains(decomposed));
+
+        let mut trie = TrieBuilder::new()
+            .compose_accents(true)
+            .case_insensitive(true)
+            .build();
+        trie.insert("E\u{301}cole", ());
+        assert!(trie.contains("école"));
+        assert_eq!(trie.words().collect::<Vec<_>>(), vec!["École"]);
+    }
+
+    #[test]
+    fn compose_accents_prefixes() {
+        let mut trie = TrieBuilder::new().compose_accents(true).build();
+        trie.insert("café", ());
+        trie.insert("café au lait", ());
+
+        let text = "cafe\u{301} au lait!";
+        assert_eq!(trie.longest_prefix(text), Some("cafe\u{301} au lait"));
+        assert_eq!(trie.longest_prefix("cafe\u{301} noir"), Some("cafe\u{301}"));
+        assert_eq!(trie.longest_prefix("café au"), Some("café"));
+        assert_eq!(trie.longest_prefix("cafe au lait"), None);
+
+        trie.delete("café");
+        assert_eq!(
+            trie.longest_common_prefix_with("cafe\u{301}"),
+            "café au lait"
+        );
+        trie.insert("café noir", ());
+        assert_eq!(trie.longest_common_prefix_with("cafe\u{301}"), "café ");
+        assert_eq!(trie.longest_common_prefix_with("café"), "café ");
+
+        let mut trie = TrieBuilder::new()
+            .compose_accents(true)
+            .case_insensitive(true)
+            .build();
+        trie.insert("École", ());
+        assert_eq!(trie.longest_prefix("E\u{301}COLE!"), Some("E\u{301}COLE"));
+    }
+
+    #[test]
+    fn contains_cache() {
+        let mut trie = TrieBuilder::new()
+            .contains_cache(500)
+            .case_insensitive(true)
+            .build();
+        let words = (0..500).map(|i| format!("Word {}", i)).collect::<Vec<_>>();
+        for word in &words {
+            trie.insert(word, ());
+        }
+
+        // No inserted word is rejected, in any casing
+        assert!(words.iter().all(|word| trie.contains(word)));
+        assert!(words.iter().all(|word| trie.contains(&word.to_uppercase())));
+
+        // Most absent words are rejected by the filter, without descending into the trie
+        let misses = (0..500).map(|i| format!("word {}!", i)).collect::<Vec<_>>();
+        assert!(!misses.iter().any(|word| trie.contains(word)));
+        let cache = trie.contains_cache.as_ref().unwrap();
+        let descents = misses.iter().filter(|word| cache.may_contain(word)).count();
+        assert!(descents < 25, "{} of 500 misses descended", descents);
+
+        // A deleted word is only absent from the trie, and cleared words from the filter too
+        trie.delete("Word 7");
+        assert!(!trie.contains("Word 7"));
+        trie.clear();
+        let cache = trie.contains_cache.as_ref().unwrap();
+        assert!(!words.iter().any(|word| cache.may_contain(word)));
+        trie.insert("Word 7", ());
+        assert!(trie.contains("word 7"));
+    }
+}
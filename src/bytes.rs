@@ -0,0 +1,164 @@
+//! This module provides the [`ByteTrie`], a trie whose keys are arbitrary bytes.
+
+use std::{collections::BTreeMap, mem};
+
+/// A trie whose keys are byte slices, rather than strings.
+///
+/// Unlike the [`Trie`](crate::Trie), which walks the characters of a word, every node of a
+/// `ByteTrie` is keyed by a single byte. This allows storing keys which aren't valid UTF-8, like
+/// binary data, without having to decode them. The keys are yielded in lexicographic order of
+/// their bytes.
+///
+/// # Examples
+///
+/// ```
+/// use treeline::ByteTrie;
+///
+/// let mut trie = ByteTrie::new();
+/// trie.insert(b"cargo");
+/// trie.insert(&[0xff, 0x00, 0xfe]);
+///
+/// assert!(trie.contains(b"cargo"));
+/// assert!(trie.contains(&[0xff, 0x00, 0xfe]));
+/// assert!(!trie.contains(&[0xff]));
+/// assert_eq!(
+///     trie.keys().collect::<Vec<_>>(),
+///     vec![b"cargo".to_vec(), vec![0xff, 0x00, 0xfe]]
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ByteTrie {
+    /// The root node, which stands for the empty key.
+    root: Node,
+    /// The number of keys in the trie.
+    key_count: usize,
+}
+
+/// A node in a [`ByteTrie`].
+#[derive(Debug, Clone, Default)]
+struct Node {
+    /// The nodes following this node, indexed by their byte.
+    children: BTreeMap<u8, Node>,
+    /// Whether the path from the root to this node is a complete key.
+    is_key: bool,
+}
+
+impl ByteTrie {
+    /// Create an empty byte trie.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts the `key` into the trie.
+    pub fn insert(&mut self, key: &[u8]) {
+        let mut node = &mut self.root;
+        for byte in key {
+            node = node.children.entry(*byte).or_default();
+        }
+
+        if !mem::replace(&mut node.is_key, true) {
+            self.key_count += 1;
+        }
+    }
+
+    /// Returns whether the `key` was inserted into the trie as a complete key.
+    pub fn contains(&self, key: &[u8]) -> bool {
+        self.root.find(key).is_some_and(|node| node.is_key)
+    }
+
+    /// Returns the number of keys in the trie.
+    pub fn len(&self) -> usize {
+        self.key_count
+    }
+
+    /// Returns whether the trie contains no keys.
+    pub fn is_empty(&self) -> bool {
+        self.key_count == 0
+    }
+
+    /// Returns an iterator over all the keys in the trie, in lexicographic order.
+    pub fn keys(&self) -> impl Iterator<Item = Vec<u8>> {
+        self.keys_with_prefix(&[])
+    }
+
+    /// Returns an iterator over the keys in the trie with the given prefix, in lexicographic order.
+    ///
+    /// Like [`Trie::words_with_prefix`](crate::Trie::words_with_prefix), the `prefix` itself is
+    /// yielded first if it is a key.
+    pub fn keys_with_prefix(&self, prefix: &[u8]) -> impl Iterator<Item = Vec<u8>> {
+        let mut keys = Vec::new();
+        if let Some(node) = self.root.find(prefix) {
+            node.collect_keys(&mut prefix.to_vec(), &mut keys);
+        }
+
+        keys.into_iter()
+    }
+}
+
+impl Node {
+    /// Returns the node at which the `key` ends, or [`None`] if no key starts with the `key`.
+    fn find(&self, key: &[u8]) -> Option<&Self> {
+        key.iter()
+            .try_fold(self, |node, byte| node.children.get(byte))
+    }
+
+    /// Pushes the keys under and including the current node onto `keys`, where `path` is the path
+    /// from the root up to and including the current node.
+    fn collect_keys(&self, path: &mut Vec<u8>, keys: &mut Vec<Vec<u8>>) {
+        if self.is_key {
+            keys.push(path.clone());
+        }
+
+        for (byte, child) in &self.children {
+            path.push(*byte);
+            child.collect_keys(path, keys);
+            path.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ByteTrie;
+    use std::str;
+
+    #[test]
+    fn insert_and_contains() {
+        let mut trie = ByteTrie::new();
+        assert!(trie.is_empty());
+
+        trie.insert(b"Hello world");
+        trie.insert(b"Hello");
+        trie.insert(b"Hello");
+
+        assert_eq!(trie.len(), 2);
+        assert!(trie.contains(b"Hello"));
+        assert!(!trie.contains(b"Hell"));
+        assert!(!trie.contains(b"Goedemorgen"));
+        assert_eq!(
+            trie.keys_with_prefix(b"Hello").collect::<Vec<_>>(),
+            vec![b"Hello".to_vec(), b"Hello world".to_vec()]
+        );
+        assert_eq!(trie.keys_with_prefix(b"Hello w").count(), 1);
+        assert_eq!(trie.keys_with_prefix(b"x").next(), None);
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let keys: [&[u8]; 4] = [&[0xff, 0xfe], &[0xc3], &[0xc3, 0x28], &[0x80]];
+        assert!(keys.iter().all(|key| str::from_utf8(key).is_err()));
+
+        let mut trie = ByteTrie::new();
+        for key in &keys {
+            trie.insert(key);
+        }
+
+        assert_eq!(trie.len(), 4);
+        assert!(keys.iter().all(|key| trie.contains(key)));
+        assert!(!trie.contains(&[0xff]));
+        assert_eq!(
+            trie.keys().collect::<Vec<_>>(),
+            vec![vec![0x80], vec![0xc3], vec![0xc3, 0x28], vec![0xff, 0xfe]]
+        );
+    }
+}
@@ -0,0 +1,107 @@
+//! This module provides the [`ContainsCache`], a Bloom filter of the words in a [`Trie`].
+//!
+//! [`Trie`]: crate::Trie
+
+/// The number of bits set for every word, which gives a false positive rate of about 1% with
+/// [`BITS_PER_WORD`] bits per word.
+const HASHES: u64 = 7;
+
+/// The number of bits reserved for every expected word.
+const BITS_PER_WORD: usize = 10;
+
+/// A Bloom filter of the words inserted into a [`Trie`], see
+/// [`TrieBuilder::contains_cache`](crate::TrieBuilder::contains_cache).
+///
+/// The filter answers whether a word may have been inserted. It never answers no for an inserted
+/// word, but may answer yes for a word which wasn't, so only a no is definite. Words can't be
+/// removed from the filter, it can only be cleared as a whole.
+///
+/// [`Trie`]: crate::Trie
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct ContainsCache {
+    /// The bits of the filter, 64 per element.
+    bits: Vec<u64>,
+}
+
+impl ContainsCache {
+    /// Creates an empty filter, sized to keep the false positive rate low for up to
+    /// `expected_words` words.
+    pub(crate) fn new(expected_words: usize) -> Self {
+        let len = (expected_words.max(1) * BITS_PER_WORD).div_ceil(64);
+        Self { bits: vec![0; len] }
+    }
+
+    /// Sets the bits of the `word`.
+    pub(crate) fn insert(&mut self, word: &str) {
+        for index in self.indices(word) {
+            self.bits[index / 64] |= 1 << (index % 64);
+        }
+    }
+
+    /// Returns whether the `word` may have been inserted, `false` means it definitely wasn't.
+    pub(crate) fn may_contain(&self, word: &str) -> bool {
+        self.indices(word)
+            .all(|index| self.bits[index / 64] & (1 << (index % 64)) != 0)
+    }
+
+    /// Removes all the words from the filter.
+    pub(crate) fn clear(&mut self) {
+        self.bits.iter_mut().for_each(|bits| *bits = 0);
+    }
+
+    /// Returns the indices of the bits of the `word`.
+    ///
+    /// The indices are derived from two hashes of the `word`, as `h1 + i * h2`, which is as good
+    /// as using a separate hash function for every index.
+    fn indices(&self, word: &str) -> impl Iterator<Item = usize> {
+        let hash = fnv1a(word.as_bytes());
+        let (h1, h2) = (hash & 0xffff_ffff, (hash >> 32) | 1);
+        let len = self.bits.len() as u64 * 64;
+        (0..HASHES).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % len) as usize)
+    }
+}
+
+/// Returns the 64-bit FNV-1a hash of `bytes`.
+///
+/// Unlike the hasher of the standard library, this hash is stable across Rust versions, so a
+/// serialized filter stays valid.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fnv1a, ContainsCache};
+
+    #[test]
+    fn no_false_negatives() {
+        let mut cache = ContainsCache::new(1000);
+        let words = (0..1000)
+            .map(|i| format!("cargo test -- test_{}", i))
+            .collect::<Vec<_>>();
+        for word in &words {
+            cache.insert(word);
+        }
+
+        assert!(words.iter().all(|word| cache.may_contain(word)));
+
+        // About 1% of the words which weren't inserted are let through
+        let misses = (0..1000)
+            .map(|i| format!("git commit -- {}", i))
+            .filter(|word| cache.may_contain(word))
+            .count();
+        assert!(misses < 50, "{} false positives", misses);
+
+        cache.clear();
+        assert!(!words.iter().any(|word| cache.may_contain(word)));
+    }
+
+    #[test]
+    fn fnv1a_hashes() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+}
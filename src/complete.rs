@@ -0,0 +1,304 @@
+//! This module provides [`complete`], which finds the completions of a prefix in a [`Trie`]
+//! independently of any UI.
+
+use crate::Trie;
+
+/// A source of completions, e.g. the history of a REPL, or a fixed list of commands.
+pub trait CompletionSource {
+    /// Returns the completions of `prefix`.
+    fn complete(&self, prefix: &str) -> Vec<String>;
+}
+
+impl<V> CompletionSource for Trie<V> {
+    /// Returns the words in the trie starting with `prefix`, sorted alphabetically.
+    fn complete(&self, prefix: &str) -> Vec<String> {
+        complete(self, prefix, CompleteOptions::default())
+    }
+}
+
+impl CompletionSource for Vec<String> {
+    /// Returns the strings starting with `prefix`, in their original order.
+    fn complete(&self, prefix: &str) -> Vec<String> {
+        self.iter()
+            .filter(|word| word.starts_with(prefix))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Options controlling which completions [`complete`] returns, and in which order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompleteOptions {
+    /// Ignore case when matching the prefix against the words in the trie.
+    pub case_insensitive: bool,
+    /// Match words which contain the characters of the prefix in order, but not necessarily
+    /// adjacent, e.g. "gco" matches "git checkout".
+    pub fuzzy: bool,
+    /// The maximum number of completions to return, [`None`] returns all completions.
+    pub max_results: Option<usize>,
+    /// The order in which the completions are returned.
+    pub ranking: Ranking,
+}
+
+/// The order in which [`complete`] returns its completions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Ranking {
+    /// Sort the completions alphabetically.
+    #[default]
+    Alphabetical,
+    /// Sort the completions by length (in characters), shortest first, and alphabetically if
+    /// their lengths are equal.
+    Shortest,
+    /// Sort the completions by insertion order, most recently inserted first.
+    MostRecent,
+}
+
+/// Returns the words in `history` which complete `prefix`, according to the given `opts`.
+///
+/// # Examples
+///
+/// ```
+/// use treeline::{complete, CompleteOptions, Ranking, Trie};
+///
+/// let mut history = Trie::new();
+/// history.insert("git status", ());
+/// history.insert("git checkout", ());
+/// history.insert("Git push", ());
+///
+/// let opts = CompleteOptions {
+///     case_insensitive: true,
+///     max_results: Some(2),
+///     ranking: Ranking::MostRecent,
+///     ..CompleteOptions::default()
+/// };
+/// assert_eq!(complete(&history, "git", opts), vec!["Git push", "git checkout"]);
+/// ```
+pub fn complete<V>(history: &Trie<V>, prefix: &str, opts: CompleteOptions) -> Vec<String> {
+    let candidates: Box<dyn Iterator<Item = &String>> = match opts.ranking {
+        Ranking::MostRecent => Box::new(
+            history
+                .words_in_insertion_order()
+                .collect::<Vec<_>>()
+                .into_iter()
+                .rev(),
+        ),
+        // Exact prefix matches can be found directly in the trie, all other matches require
+        // examining every word
+        _ if !opts.case_insensitive && !opts.fuzzy => Box::new(history.words_with_prefix(prefix)),
+        _ => Box::new(history.words()),
+    };
+
+    let mut completions = candidates
+        .filter(|word| matches(prefix, word, opts))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    match opts.ranking {
+        Ranking::Alphabetical => completions.sort_unstable(),
+        Ranking::Shortest => completions.sort_unstable_by(|a, b| {
+            a.chars()
+                .count()
+                .cmp(&b.chars().count())
+                .then_with(|| a.cmp(b))
+        }),
+        Ranking::MostRecent => {}
+    }
+
+    if let Some(max_results) = opts.max_results {
+        completions.truncate(max_results);
+    }
+
+    completions
+}
+
+/// Returns whether `word` is a completion of `prefix`, according to the given `opts`.
+fn matches(prefix: &str, word: &str, opts: CompleteOptions) -> bool {
+    match_positions(prefix, word, opts).is_some()
+}
+
+/// Returns the positions (in characters) of the characters in `word` which match the characters
+/// of `prefix`, or [`None`] if `word` isn't a completion of `prefix`, according to the given
+/// `opts`.
+///
+/// Only [`CompleteOptions::case_insensitive`] and [`CompleteOptions::fuzzy`] are considered. A
+/// fuzzy match matches each character of `prefix` with its first occurrence in `word` after the
+/// previous match.
+///
+/// # Examples
+///
+/// ```
+/// use treeline::{match_positions, CompleteOptions};
+///
+/// let opts = CompleteOptions {
+///     fuzzy: true,
+///     ..CompleteOptions::default()
+/// };
+/// assert_eq!(match_positions("gco", "git-checkout", opts), Some(vec![0, 4, 9]));
+/// ```
+pub fn match_positions(prefix: &str, word: &str, opts: CompleteOptions) -> Option<Vec<usize>> {
+    let fold = |c: char| {
+        if opts.case_insensitive {
+            c.to_lowercase().next().unwrap_or(c)
+        } else {
+            c
+        }
+    };
+
+    let mut word = word.chars().map(fold).enumerate();
+    prefix
+        .chars()
+        .map(fold)
+        .map(|p| {
+            if opts.fuzzy {
+                word.find(|(_, c)| *c == p).map(|(position, _)| position)
+            } else {
+                word.next()
+                    .filter(|(_, c)| *c == p)
+                    .map(|(position, _)| position)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{complete, match_positions, CompleteOptions, CompletionSource, Ranking};
+    use crate::Trie;
+
+    fn history() -> Trie {
+        let mut history = Trie::new();
+        history.insert("git status", ());
+        history.insert("git checkout", ());
+        history.insert("cargo build", ());
+        history.insert("Git push", ());
+        history.insert("git commit", ());
+        history
+    }
+
+    #[test]
+    fn default_options() {
+        let completions = complete(&history(), "git c", CompleteOptions::default());
+        assert_eq!(completions, vec!["git checkout", "git commit"]);
+    }
+
+    #[test]
+    fn completion_sources() {
+        let sources: Vec<Box<dyn CompletionSource>> = vec![
+            Box::new(history()),
+            Box::new(vec!["git commit".to_string(), "git checkout".to_string()]),
+        ];
+
+        for source in sources {
+            let mut completions = source.complete("git c");
+            completions.sort_unstable();
+            assert_eq!(completions, vec!["git checkout", "git commit"]);
+        }
+    }
+
+    #[test]
+    fn case_insensitive() {
+        let opts = CompleteOptions {
+            case_insensitive: true,
+            ..CompleteOptions::default()
+        };
+
+        let completions = complete(&history(), "GIT ", opts);
+        assert_eq!(
+            completions,
+            vec!["Git push", "git checkout", "git commit", "git status"]
+        );
+    }
+
+    #[test]
+    fn fuzzy() {
+        let opts = CompleteOptions {
+            fuzzy: true,
+            ..CompleteOptions::default()
+        };
+
+        assert_eq!(
+            complete(&history(), "gco", opts),
+            vec!["git checkout", "git commit"]
+        );
+        assert!(complete(&history(), "Gco", opts).is_empty());
+    }
+
+    #[test]
+    fn fuzzy_case_insensitive() {
+        let opts = CompleteOptions {
+            case_insensitive: true,
+            fuzzy: true,
+            ..CompleteOptions::default()
+        };
+
+        assert_eq!(
+            complete(&history(), "GS", opts),
+            vec!["Git push", "git status"]
+        );
+    }
+
+    #[test]
+    fn fuzzy_match_positions() {
+        let opts = CompleteOptions {
+            case_insensitive: true,
+            fuzzy: true,
+            ..CompleteOptions::default()
+        };
+
+        let word = "Git-Checkout";
+        let positions = match_positions("gco", word, opts).unwrap();
+        assert_eq!(positions, vec![0, 4, 9]);
+
+        let chars = word.chars().collect::<Vec<_>>();
+        for (position, p) in positions.iter().zip("gco".chars()) {
+            assert!(chars[*position].eq_ignore_ascii_case(&p));
+        }
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+
+        assert_eq!(match_positions("gx", word, opts), None);
+    }
+
+    #[test]
+    fn prefix_match_positions() {
+        let opts = CompleteOptions::default();
+
+        assert_eq!(
+            match_positions("git", "git status", opts),
+            Some(vec![0, 1, 2])
+        );
+        assert_eq!(match_positions("gs", "git status", opts), None);
+        assert_eq!(match_positions("", "git status", opts), Some(vec![]));
+    }
+
+    #[test]
+    fn max_results() {
+        let opts = CompleteOptions {
+            max_results: Some(1),
+            ..CompleteOptions::default()
+        };
+
+        assert_eq!(complete(&history(), "git", opts), vec!["git checkout"]);
+    }
+
+    #[test]
+    fn ranking() {
+        let opts = CompleteOptions {
+            ranking: Ranking::Shortest,
+            ..CompleteOptions::default()
+        };
+        assert_eq!(
+            complete(&history(), "git", opts),
+            vec!["git commit", "git status", "git checkout"]
+        );
+
+        let opts = CompleteOptions {
+            ranking: Ranking::MostRecent,
+            max_results: Some(2),
+            ..CompleteOptions::default()
+        };
+        assert_eq!(
+            complete(&history(), "git", opts),
+            vec!["git commit", "git checkout"]
+        );
+    }
+}
@@ -0,0 +1,116 @@
+//! This module composes letters followed by a combining accent into precomposed letters, see
+//! [`TrieBuilder::compose_accents`](crate::TrieBuilder::compose_accents).
+
+use std::borrow::Cow;
+
+/// The letters which can be composed with every combining accent, together with the letters they
+/// are composed into, at the same positions.
+///
+/// These are the letters of the Latin-1 Supplement and Latin Extended-A blocks which Unicode
+/// decomposes into a base letter and a single accent.
+const COMPOSITIONS: &[(char, &str, &str)] = &[
+    // Grave accent
+    ('\u{300}', "AEIOUaeiou", "ÀÈÌÒÙàèìòù"),
+    // Acute accent
+    (
+        '\u{301}',
+        "ACEILNORSUYZaceilnorsuyz",
+        "ÁĆÉÍĹŃÓŔŚÚÝŹáćéíĺńóŕśúýź",
+    ),
+    // Circumflex accent
+    (
+        '\u{302}',
+        "ACEGHIJOSUWYaceghijosuwy",
+        "ÂĈÊĜĤÎĴÔŜÛŴŶâĉêĝĥîĵôŝûŵŷ",
+    ),
+    // Tilde
+    ('\u{303}', "AINOUainou", "ÃĨÑÕŨãĩñõũ"),
+    // Macron
+    ('\u{304}', "AEIOUaeiou", "ĀĒĪŌŪāēīōū"),
+    // Breve
+    ('\u{306}', "AEGIOUaegiou", "ĂĔĞĬŎŬăĕğĭŏŭ"),
+    // Dot above
+    ('\u{307}', "CEGIZcegz", "ĊĖĠİŻċėġż"),
+    // Diaeresis
+    ('\u{308}', "AEIOUYaeiouy", "ÄËÏÖÜŸäëïöüÿ"),
+    // Ring above
+    ('\u{30a}', "AUau", "ÅŮåů"),
+    // Double acute accent
+    ('\u{30b}', "OUou", "ŐŰőű"),
+    // Caron
+    ('\u{30c}', "CDELNRSTZcdelnrstz", "ČĎĚĽŇŘŠŤŽčďěľňřšťž"),
+    // Cedilla
+    ('\u{327}', "CGKLNRSTcgklnrst", "ÇĢĶĻŅŖŞŢçģķļņŗşţ"),
+    // Ogonek
+    ('\u{328}', "AEIUaeiu", "ĄĘĮŲąęįų"),
+];
+
+/// Returns the letter into which `base` followed by the combining `accent` is composed, or
+/// [`None`] if there is no such letter.
+pub(crate) fn compose_pair(base: char, accent: char) -> Option<char> {
+    let (_, bases, composed) = COMPOSITIONS.iter().find(|(mark, _, _)| *mark == accent)?;
+    let index = bases.chars().position(|c| c == base)?;
+    composed.chars().nth(index)
+}
+
+/// Returns the `word` with every letter followed by a combining accent replaced by the
+/// precomposed letter, e.g. "e\u{301}" by "é".
+///
+/// This approximates the Unicode Normalization Form C for accented Latin letters, without the
+/// tables of the full normalization. Other combinations, like a letter followed by several
+/// accents, are kept as is. The `word` is only copied if anything was composed.
+pub(crate) fn compose(word: &str) -> Cow<'_, str> {
+    let mut pairs = word.chars().zip(word.chars().skip(1));
+    if !pairs.any(|(c, accent)| compose_pair(c, accent).is_some()) {
+        return Cow::Borrowed(word);
+    }
+
+    let mut composed = String::with_capacity(word.len());
+    let mut chars = word.chars().peekable();
+    while let Some(c) = chars.next() {
+        match chars.peek().and_then(|&accent| compose_pair(c, accent)) {
+            Some(letter) => {
+                composed.push(letter);
+                chars.next();
+            }
+            None => composed.push(c),
+        }
+    }
+
+    Cow::Owned(composed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compose, COMPOSITIONS};
+    use std::borrow::Cow;
+
+    #[test]
+    fn composes_accents() {
+        assert_eq!(compose("cafe\u{301}"), "café");
+        assert_eq!(compose("e\u{301}cole"), "école");
+        assert_eq!(compose("A\u{30a}ngstro\u{308}m"), "Ångström");
+        assert_eq!(compose("C\u{30c}esky\u{301}"), "Český");
+        assert!(matches!(compose("café"), Cow::Borrowed("café")));
+        assert!(matches!(compose("cargo"), Cow::Borrowed(_)));
+        assert_eq!(compose(""), "");
+    }
+
+    #[test]
+    fn keeps_other_combinations() {
+        // There is no precomposed "q" with an acute accent
+        assert_eq!(compose("q\u{301}"), "q\u{301}");
+        // Only the first of several accents is composed
+        assert_eq!(compose("e\u{301}\u{308}"), "é\u{308}");
+        // An accent at the start of the word has no letter to compose with
+        assert_eq!(compose("\u{301}e"), "\u{301}e");
+        assert_eq!(compose("漢\u{301}字"), "漢\u{301}字");
+    }
+
+    #[test]
+    fn compositions_line_up() {
+        for (_, bases, composed) in COMPOSITIONS {
+            assert_eq!(bases.chars().count(), composed.chars().count());
+        }
+    }
+}
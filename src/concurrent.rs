@@ -0,0 +1,188 @@
+//! This module provides the [`ConcurrentTrie`], a thread-safe wrapper around a [`Trie`].
+
+use crate::Trie;
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// A [`Trie`] which can be shared between threads.
+///
+/// The trie is stored inside an [`Arc<RwLock<Trie>>`], cloning a `ConcurrentTrie` is cheap and
+/// yields a handle to the same underlying trie.
+///
+/// # Locking
+///
+/// Every method acquires the lock for the duration of the call only. Writes ([`insert`]) take the
+/// write lock, and block until all readers are done. Reads take the read lock, and can run
+/// concurrently with other reads. Because the lock can't be held across an iteration, the
+/// methods returning words clone them into a [`Vec`], which is a snapshot of the trie at the
+/// moment of the call.
+///
+/// Lock poisoning is ignored, a thread panicking while holding the lock can at worst leave part of
+/// a word behind, which doesn't make the trie unusable for other threads.
+///
+/// A trie which isn't changed after it is built doesn't need the lock, it can be shared as an
+/// [`Arc<Trie>`] and queried directly.
+///
+/// [`insert`]: ConcurrentTrie::insert
+///
+/// # Examples
+///
+/// ```
+/// use std::thread;
+/// use treeline::ConcurrentTrie;
+///
+/// let trie = ConcurrentTrie::new();
+///
+/// let writer = trie.clone();
+/// thread::spawn(move || writer.insert("Hello world"))
+///     .join()
+///     .unwrap();
+///
+/// assert_eq!(trie.words_with_prefix("Hello"), vec!["Hello world"]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ConcurrentTrie {
+    /// The shared trie.
+    inner: Arc<RwLock<Trie>>,
+}
+
+impl ConcurrentTrie {
+    /// Create an empty thread-safe trie.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts the `word` into the trie, see [`Trie::insert`].
+    pub fn insert(&self, word: &str) -> bool {
+        self.write().insert(word, ())
+    }
+
+    /// Returns whether the `word` is present in the trie, see [`Trie::contains`].
+    pub fn contains(&self, word: &str) -> bool {
+        self.read().contains(word)
+    }
+
+    /// Returns a snapshot of the words in the trie with the given prefix.
+    pub fn words_with_prefix(&self, prefix: &str) -> Vec<String> {
+        self.read().words_with_prefix(prefix).cloned().collect()
+    }
+
+    /// Returns a snapshot of all the words in the trie.
+    pub fn words(&self) -> Vec<String> {
+        self.read().words().cloned().collect()
+    }
+
+    /// Calls `f` with a shared reference to the trie, while holding the read lock.
+    ///
+    /// This can be used to run queries which aren't exposed on the `ConcurrentTrie` itself,
+    /// without cloning the results. The lock is held until `f` returns, so `f` should be short.
+    pub fn with_trie<R>(&self, f: impl FnOnce(&Trie) -> R) -> R {
+        f(&self.read())
+    }
+
+    /// Acquires the read lock, ignoring poisoning.
+    fn read(&self) -> RwLockReadGuard<'_, Trie> {
+        self.inner.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Acquires the write lock, ignoring poisoning.
+    fn write(&self) -> RwLockWriteGuard<'_, Trie> {
+        self.inner.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl From<Trie> for ConcurrentTrie {
+    fn from(trie: Trie) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(trie)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConcurrentTrie;
+    use crate::Trie;
+    use std::{sync::Arc, thread};
+
+    #[test]
+    fn insert_and_read_from_threads() {
+        let trie = ConcurrentTrie::new();
+
+        let writers = (0..4)
+            .map(|i| {
+                let trie = trie.clone();
+                thread::spawn(move || {
+                    for j in 0..50 {
+                        assert!(trie.insert(&format!("thread {} word {:02}", i, j)));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let readers = (0..4)
+            .map(|i| {
+                let trie = trie.clone();
+                thread::spawn(move || {
+                    for j in 0..50 {
+                        for word in trie.words_with_prefix(&format!("thread {} ", i)) {
+                            assert!(word.starts_with(&format!("thread {} ", i)));
+                            assert!(trie.contains(&word));
+                        }
+                        assert!(!trie.contains(&format!("thread {} word {:02}!", i, j)));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for handle in writers.into_iter().chain(readers) {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(trie.words().len(), 4 * 50);
+        assert_eq!(trie.words_with_prefix("thread 2 ").len(), 50);
+        assert!(trie.contains("thread 3 word 49"));
+        assert!(!trie.contains("thread 3 word"));
+    }
+
+    #[test]
+    fn shared_trie_from_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Trie>();
+        assert_send_sync::<Trie<String>>();
+
+        let trie = Arc::new(
+            (0..4)
+                .flat_map(|i| (0..50).map(move |j| format!("thread {} word {:02}", i, j)))
+                .collect::<Trie>(),
+        );
+
+        let readers = (0..8)
+            .map(|i| {
+                let trie = Arc::clone(&trie);
+                thread::spawn(move || {
+                    let prefix = format!("thread {} ", i % 4);
+                    (0..50)
+                        .map(|_| trie.words_with_prefix(&prefix).cloned().collect::<Vec<_>>())
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for (i, handle) in readers.into_iter().enumerate() {
+            let expected = (0..50)
+                .map(|j| format!("thread {} word {:02}", i % 4, j))
+                .collect::<Vec<_>>();
+            for words in handle.join().unwrap() {
+                assert_eq!(words, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn with_trie() {
+        let trie = ConcurrentTrie::new();
+        trie.insert("Hello world!");
+
+        assert!(trie.with_trie(|trie| trie.find("Hello").is_some()));
+    }
+}
@@ -1,367 +1,3037 @@
-//! This module provides the [`Trie`] datastructure, a type of search tree.
-#![warn(missing_docs, broken_intra_doc_links)]
-
-use std::collections::HashMap;
-
-/// The [Trie] datastructure.
-///
-/// The current implementation uses [`Node`]s to store the values inside the trie. Each [`Node`]
-/// has a key and a value associated with it. The key is the last character of the value, and is
-/// used as an index into the [`Node::children`] [`HashMap`]. The value contains the word which
-/// would be found when traversing the trie from the root to that node.
-///
-/// To iterate over the words inside the trie, the user has two options: they can either iterate
-/// over all the words in the trie, or they can iterate over the words with a given prefix.
-///
-/// [Trie]: https://en.wikipedia.org/wiki/Trie
-///
-/// # Examples
-///
-/// A basic example, showing how to insert a word into the trie, and how to iterate over the words
-/// in the trie.
-///
-/// ```
-/// use trie::Trie;
-///
-/// let mut trie = Trie::new();
-///
-/// trie.insert("Hello world");
-/// trie.insert("Goedemorgen");
-/// trie.insert("Hello sir");
-///
-/// // Iterate over all the words in the trie
-/// for word in trie.words() {
-///     println("Found word: {}", word);
-/// }
-///
-/// // Iterate over all the words starting with 'Hello'
-/// for word in trie.words_with_prefix("Hello") {
-///     println("Found word: {}", word);
-/// }
-/// ```
-#[derive(Debug)]
-pub struct Trie {
-    /// The root node inside the trie.
-    ///
-    /// This node serves no other purpose besides providing an easy way to access the nodes in the
-    /// trie. The key and value shouldn't be read, as they have no meaning, and only serve as
-    /// placeholders, to prevent us from having to store them inside an [`Option`], which wouldn't
-    /// make sense as the key and value properties are mandatory on a [`Node`].
-    root: Node,
-}
-
-impl Trie {
-    /// Create an empty trie datastructure.
-    pub fn new() -> Self {
-        Self {
-            root: Node::new(' ', String::new()),
-        }
-    }
-
-    /// Inserts the `word` into the trie.
-    ///
-    /// If a part of the `word` is not yet present in the trie, that part is added. The already
-    /// existing part of the `word` is unchanged.
-    pub fn insert(&mut self, word: &str) {
-        self.root.insert(word);
-    }
-
-    /// Deletes the `word` from the trie.
-    ///
-    /// Only the part that is not part of another word will be removed, if part of the `word` is a
-    /// prefix of another word in the trie, that part will not be removed.
-    pub fn delete(&mut self, word: &str) {
-        self.root.delete(word);
-    }
-
-    /// Deletes the `word` from the trie after the `prefix`, leaving the `prefix` intact.
-    pub fn delete_after_prefix(&mut self, prefix: &str, word: &str) {
-        if let Some(head) = self.root.find_mut(prefix) {
-            head.delete(word);
-        }
-    }
-
-    /// Returns a reference to the [`Node`] containing the last character of the `word`.
-    pub fn find(&self, word: &str) -> Option<&Node> {
-        self.root.find(word)
-    }
-
-    /// Returns a mutable reference to the [`Node`] containing the last character of the `word`.
-    fn _find_mut(&mut self, word: &str) -> Option<&mut Node> {
-        self.root.find_mut(word)
-    }
-
-    /// Returns an iterator over the words in the trie with the given prefix.
-    pub fn words_with_prefix(&self, prefix: &str) -> TrieRead {
-        let stack = if let Some(head) = self.find(prefix) {
-            head.children.values().collect::<Vec<_>>()
-        } else {
-            vec![]
-        };
-
-        TrieRead { stack }
-    }
-
-    /// Returns an iterator over all the words in the trie.
-    pub fn words(&self) -> TrieRead {
-        TrieRead {
-            stack: self.root.children.values().collect::<Vec<_>>(),
-        }
-    }
-}
-
-/// Iterator over the words in a [`Trie`]
-///
-/// This iterator is returned from the [`Trie::words_with_prefix`] function on a [`Trie`] and will
-/// yield instances of [`String`].
-pub struct TrieRead<'a> {
-    /// Stack to keep track of which [`Node`]s we still need to visit while iterating over the
-    /// words in the trie.
-    stack: Vec<&'a Node>,
-}
-
-impl<'a> Iterator for TrieRead<'a> {
-    type Item = &'a String;
-
-    // Iterates over the words in the trie using depth-first search
-    fn next(&mut self) -> Option<Self::Item> {
-        while let Some(head) = self.stack.pop() {
-            // Store the children on the stack, to be examined later
-            for child in head.children.values() {
-                self.stack.push(child);
-            }
-
-            // If a node has no children, it is the end of a word, and we should return the
-            // value, since that will contain a complete word. If the node does have children,
-            // we don't return here, but simply continue looping until we either reach a node
-            // containing a complete word, or we run out of nodes.
-            if head.children.is_empty() {
-                return Some(&head.value);
-            }
-        }
-
-        None
-    }
-}
-
-/// A `Node` in a [`Trie`].
-#[derive(Debug)]
-pub struct Node {
-    /// The last character of the word stored in the value.
-    key: char,
-    /// Contains the word which would be found when traversing the trie from the root to this node.
-    value: String,
-    /// The children, i.e. words which have `value` as a prefix.
-    children: HashMap<char, Node>,
-}
-
-impl Node {
-    /// Creates a new `Node` with the given key and value.
-    fn new(key: char, value: String) -> Self {
-        Self {
-            key,
-            value,
-            children: HashMap::new(),
-        }
-    }
-
-    /// Inserts the `word` under the current node.
-    ///
-    /// If a part of the `word` is not yet present under the current node, that part is added. The
-    /// already existing part of the `word` is unchanged.
-    fn insert(&mut self, word: &str) {
-        if let Some(root) = word.chars().next() {
-            let prefix = self.value.clone();
-            let root = self
-                .children
-                .entry(root)
-                .or_insert_with(|| Node::new(root, format!("{}{}", prefix, root)));
-            root.insert(&word[1..]);
-        }
-    }
-
-    /// Deletes the word under the current node.
-    ///
-    /// Only the part that is not part of another word will be removed, if part of the word is a
-    /// prefix of another word under the current node, that part will not be removed.
-    fn delete(&mut self, word: &str) {
-        if let Some(root) = word.chars().next() {
-            if let Some(child) = self.children.get_mut(&root) {
-                // If `child` doesn't have any children, it is the last node in the word, and can
-                // thus safely be removed
-                if child.children.is_empty() {
-                    self.children.remove(&root);
-                } else {
-                    // Firstly, try to delete the remainder of the word
-                    child.delete(&word[1..]);
-
-                    // Secondly, if `child` has no more children left, it can be safely removed.
-                    // This can be the case when there are a few nodes with only 1 child, this
-                    // takes care that we remove them recursively.
-                    if child.children.is_empty() {
-                        self.children.remove(&root);
-                    }
-                }
-            }
-        }
-    }
-
-    /// Returns a reference to the [`Node`] containing the last character of the `word`.
-    pub fn find(&self, word: &str) -> Option<&Self> {
-        if let Some(root) = word.chars().next() {
-            if let Some(child) = self.children.get(&root) {
-                return child.find(&word[1..]);
-            } else {
-                return None;
-            }
-        }
-
-        Some(self)
-    }
-
-    /// Returns a mutable reference to the [`Node`] containing the last character of the `word`.
-    fn find_mut(&mut self, word: &str) -> Option<&mut Self> {
-        if let Some(root) = word.chars().next() {
-            if let Some(child) = self.children.get_mut(&root) {
-                return child.find_mut(&word[1..]);
-            } else {
-                return None;
-            }
-        }
-
-        Some(self)
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::Trie;
-
-    #[test]
-    fn insert_single() {
-        let mut trie = Trie::new();
-        let input = "Hello world!";
-
-        trie.insert(input);
-        assert!(trie.find(input).is_some());
-        assert!(trie.find("Hi there").is_none());
-    }
-
-    #[test]
-    fn insert_multiple() {
-        let mut trie = Trie::new();
-        let input1 = "Hello world!";
-        let input2 = "Hello sir!";
-        let input3 = "Good afternoon!";
-
-        trie.insert(input1);
-        trie.insert(input2);
-        trie.insert(input3);
-
-        assert!(trie.find(input1).is_some());
-        assert!(trie.find(input2).is_some());
-        assert!(trie.find(input3).is_some());
-        assert!(trie.find("Hi there").is_none());
-    }
-
-    #[test]
-    fn delete_single() {
-        let mut trie = Trie::new();
-        let input = "Hello world!";
-
-        trie.insert(input);
-        assert!(trie.find(input).is_some());
-
-        trie.delete(input);
-        assert!(trie.find(input).is_none());
-
-        assert_eq!(len(&trie), 0);
-    }
-
-    #[test]
-    fn delete_prefix() {
-        let mut trie = Trie::new();
-        let input = "Hello world!";
-
-        trie.insert(input);
-        assert!(trie.find(input).is_some());
-
-        trie.delete("Hello");
-        assert!(trie.find(input).is_some());
-
-        assert_eq!(len(&trie), 1);
-    }
-
-    #[test]
-    fn delete_after_prefix() {
-        let mut trie = Trie::new();
-        let input = "Hello world!";
-
-        trie.insert(input);
-        assert!(trie.find(input).is_some());
-
-        trie.delete_after_prefix("Hello ", "world!");
-        assert!(trie.find(input).is_none());
-
-        assert_eq!(len(&trie), 1);
-    }
-
-    #[test]
-    fn delete_multiple() {
-        let mut trie = Trie::new();
-        let input1 = "Hello world!";
-        let input2 = "Hello sir!";
-        let input3 = "Good afternoon!";
-
-        trie.insert(input1);
-        trie.insert(input2);
-        trie.insert(input3);
-
-        assert!(trie.find(input1).is_some());
-        assert!(trie.find(input2).is_some());
-        assert!(trie.find(input3).is_some());
-
-        trie.delete(input1);
-        assert!(trie.find(input1).is_none());
-
-        trie.delete(input3);
-        assert!(trie.find(input3).is_none());
-
-        assert_eq!(len(&trie), 1);
-    }
-
-    #[test]
-    fn find_in_empty_trie() {
-        let trie = Trie::new();
-
-        assert!(trie.find(" ").is_none());
-    }
-
-    #[test]
-    fn find_prefix() {
-        let mut trie = Trie::new();
-        trie.insert("Hello world!");
-
-        assert!(trie.find("Hello").is_some());
-    }
-
-    #[test]
-    fn find_from_prefix() {
-        let mut trie = Trie::new();
-        trie.insert("Hello world!");
-        trie.insert("Hello sir!");
-        trie.insert("Hello miss!");
-
-        if let Some(node) = trie.find("Hello ") {
-            assert!(node.find("sir").is_some());
-        }
-    }
-
-    fn len(trie: &Trie) -> usize {
-        let mut len = 0;
-        for _ in trie.words() {
-            len += 1;
-        }
-        len
-    }
-}
+//! This module provides the [`Trie`] datastructure, a type of search tree.
+#![warn(missing_docs, broken_intra_doc_links)]
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashSet, VecDeque},
+    fmt,
+    io::{self, BufRead, BufReader, Read, Write},
+    iter::{self, FromIterator},
+    mem,
+    ops::AddAssign,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+mod builder;
+mod bytes;
+mod cache;
+mod complete;
+mod compose;
+mod concurrent;
+mod persistent;
+mod radix;
+
+pub use builder::TrieBuilder;
+pub use bytes::ByteTrie;
+pub use complete::{complete, match_positions, CompleteOptions, CompletionSource, Ranking};
+pub use concurrent::ConcurrentTrie;
+pub use persistent::PersistentTrie;
+pub use radix::RadixTrie;
+
+/// The [Trie] datastructure.
+///
+/// The current implementation uses [`Node`]s to store the values inside the trie. Each [`Node`]
+/// has a key and a value associated with it. The key is the last character of the path from the
+/// root to the node, and is used as an index into the [`Node::children`] [`BTreeMap`]. The value
+/// contains the word ending at that node, which is only stored in the nodes of complete words.
+///
+/// Every word in the trie has a value of type `V` associated with it, which is stored in the
+/// [`Node`] containing the last character of the word. A trie which only stores words uses `()`
+/// as its value, see [`StringTrie`].
+///
+/// To iterate over the words inside the trie, the user has two options: they can either iterate
+/// over all the words in the trie, or they can iterate over the words with a given prefix.
+///
+/// [Trie]: https://en.wikipedia.org/wiki/Trie
+///
+/// # Examples
+///
+/// A basic example, showing how to insert a word into the trie, and how to iterate over the words
+/// in the trie.
+///
+/// ```
+/// use treeline::Trie;
+///
+/// let mut trie = Trie::new();
+///
+/// trie.insert("Hello world", ());
+/// trie.insert("Goedemorgen", ());
+/// trie.insert("Hello sir", ());
+///
+/// // Iterate over all the words in the trie
+/// for word in trie.words() {
+///     println!("Found word: {}", word);
+/// }
+///
+/// // Iterate over all the words starting with 'Hello'
+/// for word in trie.words_with_prefix("Hello") {
+///     println!("Found word: {}", word);
+/// }
+/// ```
+///
+/// Associating a value with every word:
+///
+/// ```
+/// use treeline::Trie;
+///
+/// let mut trie = Trie::new();
+///
+/// trie.insert("cargo build", 1);
+/// trie.insert("cargo test", 1);
+/// *trie.get_mut("cargo test").unwrap() += 1;
+///
+/// assert_eq!(trie.get("cargo test"), Some(&2));
+/// assert_eq!(trie.get("cargo"), None);
+/// ```
+///
+/// With the `serde` feature enabled, a trie can be serialized, e.g. to cache it on disk. The
+/// normalizer and clock are functions, so they aren't serialized, a deserialized trie has neither
+/// a normalizer nor a custom clock.
+///
+/// Cloning a trie copies all of its nodes, so the clone can be changed independently, e.g. to roll
+/// back a batch of changes. The normalizer and clock are shared with the clone.
+///
+/// A trie is [`Send`] and [`Sync`] when its values are, as the normalizer and clock have to be
+/// both too. A trie which isn't changed after it is built can therefore be shared in an [`Arc`],
+/// and queried from several threads at once without any locking. To insert words while other
+/// threads read, use a [`ConcurrentTrie`] instead.
+///
+/// ```
+/// use std::{sync::Arc, thread};
+/// use treeline::Trie;
+///
+/// let trie = Arc::new(Trie::from_words(["cargo build", "cargo test", "git status"]));
+///
+/// let reader = Arc::clone(&trie);
+/// let count = thread::spawn(move || reader.words_with_prefix("cargo").count())
+///     .join()
+///     .unwrap();
+///
+/// assert_eq!(count, 2);
+/// ```
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Trie<V = ()> {
+    /// The root node inside the trie.
+    ///
+    /// This node serves no other purpose besides providing an easy way to access the nodes in the
+    /// trie. The key and value shouldn't be read, as they have no meaning, and only serve as
+    /// placeholders, to prevent us from having to store them inside an [`Option`], which wouldn't
+    /// make sense as the key and value properties are mandatory on a [`Node`].
+    root: Node<V>,
+    /// The number of words in the trie.
+    word_count: usize,
+    /// Function applied to every word before it is inserted, see [`Trie::with_normalizer`].
+    #[cfg_attr(feature = "serde", serde(skip))]
+    normalizer: Option<Normalizer>,
+    /// The number of insertions into the trie, used to number the words in insertion order.
+    insertions: u64,
+    /// The maximum number of words in the trie, see [`Trie::with_lru_capacity`].
+    capacity: Option<usize>,
+    /// The words by the number of their last insertion, used to find the least recently used word.
+    /// Only kept if the trie has a capacity.
+    ///
+    /// Words deleted other than through [`Trie::delete`] are left in the index, and skipped when
+    /// evicting.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    recency: BTreeMap<u64, String>,
+    /// The clock used to timestamp insertions, see [`Trie::with_clock`].
+    #[cfg_attr(feature = "serde", serde(skip, default = "system_clock"))]
+    clock: Clock,
+    /// Whether words are matched regardless of their case, see [`Trie::new_case_insensitive`].
+    case_insensitive: bool,
+    /// Whether letters followed by a combining accent are composed, see
+    /// [`TrieBuilder::compose_accents`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    compose_accents: bool,
+    /// The Bloom filter consulted by [`Trie::contains`], see [`TrieBuilder::contains_cache`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    contains_cache: Option<cache::ContainsCache>,
+}
+
+/// A function which transforms a word before it is inserted into a [`Trie`], or rejects it by
+/// returning [`None`].
+pub type Normalizer = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+/// A function returning the current time, used by a [`Trie`] to timestamp insertions.
+pub type Clock = Arc<dyn Fn() -> SystemTime + Send + Sync>;
+
+/// Returns the [`Clock`] used by default, i.e. [`SystemTime::now`].
+fn system_clock() -> Clock {
+    Arc::new(SystemTime::now)
+}
+
+/// Returns the character which `c` is matched as in a case-insensitive [`Trie`].
+///
+/// This is the first character of the lowercase form of `c`, like in [`match_positions`].
+fn fold_case(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// Returns `c` escaped to appear in a quoted string in the DOT language, see [`Trie::to_dot`].
+fn escape_dot(c: char) -> String {
+    match c {
+        '"' | '\\' => format!("\\{}", c),
+        '\n' => String::from("\\n"),
+        _ => c.to_string(),
+    }
+}
+
+/// Pushes the `position` in the `pattern` onto `positions`, see [`Trie::words_matching`].
+///
+/// A `*` may also match no characters at all, so the positions following a `*` are pushed too.
+fn push_position(pattern: &[char], mut position: usize, positions: &mut Vec<usize>) {
+    loop {
+        if !positions.contains(&position) {
+            positions.push(position);
+        }
+        if pattern.get(position) != Some(&'*') {
+            break;
+        }
+        position += 1;
+    }
+}
+
+/// A [`Trie`] which only stores words, without associating a value with them.
+pub type StringTrie = Trie<()>;
+
+impl Trie {
+    /// Returns a [`TrieBuilder`] to create a trie with non-default options.
+    pub fn builder() -> TrieBuilder {
+        TrieBuilder::new()
+    }
+
+    /// Creates a trie containing the given words, each inserted through [`Trie::insert`].
+    ///
+    /// This is the same as collecting the words into a trie.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use treeline::Trie;
+    ///
+    /// let trie = Trie::from_words(&["cargo build", "cargo test"]);
+    /// assert!(trie.contains("cargo test"));
+    /// ```
+    pub fn from_words<I, S>(words: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut trie = Self::new();
+        trie.extend(words);
+        trie
+    }
+
+    /// Creates a trie from the words read from `reader`, one word per line, as written by
+    /// [`Trie::write_to_writer`].
+    ///
+    /// The words are inserted in the order in which they are read, so the last line is the most
+    /// recently inserted word. Empty lines are skipped, so an empty `reader` results in an empty
+    /// trie.
+    pub fn load_from_reader(reader: impl Read) -> io::Result<Self> {
+        let mut trie = Self::new();
+        trie.insert_lines(BufReader::new(reader))?;
+
+        Ok(trie)
+    }
+
+    /// Inserts every line read from `reader` into the trie as a word, in the order in which they
+    /// are read.
+    ///
+    /// The lines are inserted while they are read, so the input is never held in memory as a
+    /// whole. Empty lines are skipped. Returns the number of inserted words, excluding the lines
+    /// rejected by the trie's [`Normalizer`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Cursor;
+    /// use treeline::Trie;
+    ///
+    /// let mut dictionary = Trie::new();
+    /// let inserted = dictionary.insert_lines(Cursor::new("cargo\n\ngit\n")).unwrap();
+    ///
+    /// assert_eq!(inserted, 2);
+    /// assert!(dictionary.contains("git"));
+    /// ```
+    pub fn insert_lines<R: BufRead>(&mut self, reader: R) -> io::Result<usize> {
+        let mut inserted = 0;
+        for line in reader.lines() {
+            let line = line?;
+            if !line.is_empty() && self.insert(&line, ()) {
+                inserted += 1;
+            }
+        }
+
+        Ok(inserted)
+    }
+}
+
+impl<V> Trie<V> {
+    /// Create an empty trie datastructure.
+    pub fn new() -> Self {
+        Self {
+            root: Node::new(' '),
+            word_count: 0,
+            normalizer: None,
+            insertions: 0,
+            capacity: None,
+            recency: BTreeMap::new(),
+            clock: system_clock(),
+            case_insensitive: false,
+            compose_accents: false,
+            contains_cache: None,
+        }
+    }
+
+    /// Create an empty trie datastructure, which matches words regardless of their case.
+    ///
+    /// Every lookup, e.g. [`Trie::contains`], [`Trie::delete`] and [`Trie::words_with_prefix`],
+    /// ignores the case of the given word, while the words are yielded with the casing they were
+    /// last inserted with. Characters are compared by the first character of their lowercase form
+    /// (see [`char::to_lowercase`]), which handles most scripts, but isn't full Unicode case
+    /// folding, e.g. "ß" doesn't match "SS".
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use treeline::Trie;
+    ///
+    /// let mut trie = Trie::new_case_insensitive();
+    ///
+    /// trie.insert("Hello world", ());
+    /// assert!(trie.contains("HELLO WORLD"));
+    /// assert_eq!(trie.words_with_prefix("hello").collect::<Vec<_>>(), vec!["Hello world"]);
+    /// ```
+    pub fn new_case_insensitive() -> Self {
+        Self {
+            case_insensitive: true,
+            ..Self::new()
+        }
+    }
+
+    /// Create an empty trie datastructure, which passes every inserted word through `normalizer`.
+    ///
+    /// The `normalizer` can transform the word (e.g. trim surrounding whitespace) by returning the
+    /// transformed word, or reject it entirely by returning [`None`], in which case nothing is
+    /// inserted. The `normalizer` is only applied by [`Trie::insert`], lookups and deletions use
+    /// the word as given, as they also accept prefixes, which the `normalizer` might reject.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use treeline::Trie;
+    ///
+    /// let mut trie = Trie::with_normalizer(|word| Some(word.trim().to_string()));
+    ///
+    /// trie.insert("Hello world\n", ());
+    /// assert_eq!(trie.words().collect::<Vec<_>>(), vec!["Hello world"]);
+    /// ```
+    pub fn with_normalizer<F>(normalizer: F) -> Self
+    where
+        F: Fn(&str) -> Option<String> + Send + Sync + 'static,
+    {
+        Self {
+            normalizer: Some(Arc::new(normalizer)),
+            ..Self::new()
+        }
+    }
+
+    /// Create an empty trie datastructure, which holds at most `capacity` words.
+    ///
+    /// When an insertion causes the trie to hold more than `capacity` words, the least recently
+    /// used word is deleted. A word is used when it is inserted, so inserting a word which is
+    /// already present marks it as the most recently used word.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use treeline::Trie;
+    ///
+    /// let mut trie = Trie::with_lru_capacity(2);
+    ///
+    /// trie.insert("cargo build", ());
+    /// trie.insert("cargo test", ());
+    /// trie.insert("cargo build", ());
+    /// trie.insert("git status", ());
+    ///
+    /// assert!(trie.find("cargo test").is_none());
+    /// ```
+    pub fn with_lru_capacity(capacity: usize) -> Self {
+        Self {
+            capacity: Some(capacity),
+            ..Self::new()
+        }
+    }
+
+    /// Create an empty trie datastructure, which uses `clock` instead of [`SystemTime::now`] to
+    /// timestamp insertions.
+    ///
+    /// This is mainly useful to control the passing of time when testing [`Trie::expire`].
+    pub fn with_clock<F>(clock: F) -> Self
+    where
+        F: Fn() -> SystemTime + Send + Sync + 'static,
+    {
+        Self {
+            clock: Arc::new(clock),
+            ..Self::new()
+        }
+    }
+
+    /// Inserts the `word` into the trie, associating `value` with it.
+    ///
+    /// If a part of the `word` is not yet present in the trie, that part is added. The already
+    /// existing part of the `word` is unchanged. If the `word` is already present, its value is
+    /// replaced by `value`. If this causes the trie to exceed its
+    /// [capacity](Trie::with_lru_capacity), the least recently used word is deleted.
+    ///
+    /// Every insertion of the `word` increments its frequency, see [`Trie::top_completions`].
+    ///
+    /// The empty string isn't a word, as [`Trie::words`] couldn't yield it.
+    ///
+    /// Returns `false` if the `word` is empty or was rejected by the trie's [`Normalizer`], and
+    /// `true` otherwise.
+    pub fn insert(&mut self, word: &str, value: V) -> bool {
+        self.insert_with_frequency(word, value, 1)
+    }
+
+    /// Inserts the `word` into the trie like [`Trie::insert`], but increments its frequency by
+    /// `frequency`.
+    fn insert_with_frequency(&mut self, word: &str, value: V, frequency: u64) -> bool {
+        let word = match &self.normalizer {
+            Some(normalize) => match normalize(word) {
+                Some(word) => Cow::Owned(word),
+                None => return false,
+            },
+            None => Cow::Borrowed(word),
+        };
+        if word.is_empty() {
+            return false;
+        }
+
+        self.insert_normalized(word, value, frequency);
+
+        true
+    }
+
+    /// Inserts the `word`, which was already passed through the [`Normalizer`], into the trie like
+    /// [`Trie::insert_with_frequency`].
+    fn insert_normalized(&mut self, mut word: Cow<'_, str>, value: V, frequency: u64) {
+        // The word is stored with its accents composed, so it is yielded like it is matched
+        if self.compose_accents {
+            if let Cow::Owned(composed) = compose::compose(&word) {
+                word = Cow::Owned(composed);
+            }
+        }
+
+        // The filter holds the words as they are looked up by `Trie::contains`
+        let key = self.contains_cache.as_ref().map(|_| self.keys(&word));
+        if let (Some(cache), Some(key)) = (&mut self.contains_cache, key) {
+            cache.insert(&key);
+        }
+
+        let node = self.root.insert(&word, self.case_insensitive);
+        // Only the node ending the word stores it. In a case-insensitive trie the nodes are shared
+        // by all casings of the word, so the word is yielded with the casing it was last inserted
+        // with.
+        if node.value != word {
+            node.value = word.into_owned();
+        }
+
+        if node.data.replace(value).is_none() {
+            self.word_count += 1;
+        }
+
+        self.insertions += 1;
+        node.frequency += frequency;
+        let previous = node.seq.replace(self.insertions);
+        node.touched = Some((self.clock)());
+        if self.capacity.is_some() {
+            if let Some(previous) = previous {
+                self.recency.remove(&previous);
+            }
+            self.recency.insert(self.insertions, node.value.clone());
+        }
+
+        self.evict();
+    }
+
+    /// Returns a mutable reference to the value associated with the `word`, first inserting the
+    /// `word` with the value returned by `default` if it isn't present.
+    ///
+    /// Like [`Trie::get_mut`], the `word` is used as is, it isn't passed through the trie's
+    /// [`Normalizer`]. Looking up a word which is already present doesn't count as an insertion,
+    /// so its frequency and recency are unchanged, and `default` isn't called.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `word` is empty, as the empty string can't be inserted (see [`Trie::insert`]),
+    /// or if the trie has a [capacity](Trie::with_lru_capacity) of 0, as the inserted word would be
+    /// evicted immediately.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use treeline::Trie;
+    ///
+    /// let mut counts = Trie::new();
+    /// for word in "the cat saw the dog".split(' ') {
+    ///     *counts.get_or_insert_with(word, || 0) += 1;
+    /// }
+    ///
+    /// assert_eq!(counts.get("the"), Some(&2));
+    /// assert_eq!(counts.get("cat"), Some(&1));
+    /// ```
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, word: &str, default: F) -> &mut V {
+        assert!(!word.is_empty(), "the empty string can't be inserted");
+        let key = self.keys(word);
+        if !self.root.find(&key).is_some_and(Node::is_word) {
+            self.insert_normalized(Cow::Borrowed(word), default(), 1);
+        }
+
+        self.root
+            .find_mut(&key)
+            .and_then(|node| node.data.as_mut())
+            .expect("the word was evicted by a capacity of 0")
+    }
+
+    /// Deletes the least recently used words until the trie no longer exceeds its capacity.
+    fn evict(&mut self) {
+        let capacity = match self.capacity {
+            Some(capacity) => capacity,
+            None => return,
+        };
+
+        // The index is missing words after deserializing, and may be mostly stale after deleting
+        // many words by prefix, in both cases it is rebuilt from the nodes.
+        if self.recency.len() < self.word_count || self.recency.len() > 2 * self.word_count + 16 {
+            self.recency = self
+                .word_nodes()
+                .filter_map(|node| Some((node.seq?, node.value.clone())))
+                .collect();
+        }
+
+        while self.word_count > capacity {
+            let (seq, lru) = match self.recency.pop_first() {
+                Some(entry) => entry,
+                None => break,
+            };
+
+            if self.find(&lru).and_then(|node| node.seq) == Some(seq) {
+                self.delete(&lru);
+            }
+        }
+    }
+
+    /// Deletes the `word` from the trie.
+    ///
+    /// Only the part that is not part of another word will be removed, if part of the `word` is a
+    /// prefix of another word in the trie, that part will not be removed.
+    ///
+    /// Returns whether the `word` was present. If it is only a prefix of other words, nothing is
+    /// removed and `false` is returned.
+    pub fn delete(&mut self, word: &str) -> bool {
+        let key = self.keys(word);
+        if let Some(seq) = self.root.find(&key).and_then(|node| node.seq) {
+            self.recency.remove(&seq);
+        }
+
+        let deleted = self.root.delete(&key);
+        if deleted {
+            self.word_count -= 1;
+        }
+
+        deleted
+    }
+
+    /// Deletes every word starting with the `prefix`, including the `prefix` itself if it is a
+    /// word.
+    ///
+    /// Returns the number of deleted words, which is 0 if no word starts with the `prefix`. The
+    /// words which are a prefix of the `prefix` are kept.
+    pub fn remove_prefix(&mut self, prefix: &str) -> usize {
+        let removed = self.root.remove_prefix(&self.keys(prefix));
+        self.word_count -= removed;
+        removed
+    }
+
+    /// Deletes every word for which `keep` returns `false`, like [`Vec::retain`].
+    ///
+    /// Like [`Trie::delete`], a deleted word which is a prefix of a kept word is no longer a word,
+    /// but the nodes it shares with the kept word are left intact. The words are passed to `keep`
+    /// in lexicographic order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use treeline::Trie;
+    ///
+    /// let mut history = Trie::from_words(["ls", "cargo", "cargo test", "git status"]);
+    /// history.retain(|line| line.len() > 3);
+    ///
+    /// assert_eq!(history.words().collect::<Vec<_>>(), vec!["cargo", "cargo test", "git status"]);
+    /// ```
+    pub fn retain<F: FnMut(&str) -> bool>(&mut self, mut keep: F) {
+        self.word_count -= self.root.retain(&mut keep);
+    }
+
+    /// Deletes the words which haven't been inserted within the `older_than` window before `now`.
+    ///
+    /// Returns the number of deleted words. A word which is inserted again is refreshed, and its
+    /// window starts over.
+    pub fn expire(&mut self, older_than: Duration, now: SystemTime) -> usize {
+        let mut stale = Vec::new();
+        let mut stack = self.root.children.values().collect::<Vec<_>>();
+        while let Some(head) = stack.pop() {
+            stack.extend(head.children.values());
+
+            if head.is_word() {
+                let expired = head.touched.is_some_and(|touched| {
+                    now.duration_since(touched)
+                        .is_ok_and(|age| age > older_than)
+                });
+                if expired {
+                    stale.push(head.value.clone());
+                }
+            }
+        }
+
+        for word in &stale {
+            self.delete(word);
+        }
+
+        stale.len()
+    }
+
+    /// Deletes the `word` from the trie after the `prefix`, leaving the nodes of the `prefix`
+    /// intact.
+    ///
+    /// The `prefix` only remains a word if it was inserted as one, as there is no value to
+    /// associate with it otherwise.
+    pub fn delete_after_prefix(&mut self, prefix: &str, word: &str) {
+        let word = self.keys(word);
+        if let Some(head) = self.root.find_mut(&self.keys(prefix)) {
+            if head.delete(&word) {
+                self.word_count -= 1;
+            }
+        }
+    }
+
+    /// Returns a reference to the [`Node`] containing the last character of the `word`.
+    pub fn find(&self, word: &str) -> Option<&Node<V>> {
+        self.root.find(&self.keys(word))
+    }
+
+    /// Returns an iterator over the nodes of the words in the trie, in lexicographic order.
+    fn word_nodes(&self) -> impl Iterator<Item = &Node<V>> {
+        let mut stack = self.root.children.values().rev().collect::<Vec<_>>();
+        iter::from_fn(move || {
+            while let Some(head) = stack.pop() {
+                stack.extend(head.children.values().rev());
+                if head.is_word() {
+                    return Some(head);
+                }
+            }
+
+            None
+        })
+    }
+
+    /// Returns the `word` as it is matched against the keys of the nodes, i.e. with its accents
+    /// composed if the trie [composes accents](TrieBuilder::compose_accents), and its case folded
+    /// if the trie is case-insensitive.
+    fn keys<'a>(&self, word: &'a str) -> Cow<'a, str> {
+        let word = if self.compose_accents {
+            compose::compose(word)
+        } else {
+            Cow::Borrowed(word)
+        };
+
+        if self.case_insensitive {
+            Cow::Owned(word.chars().map(fold_case).collect())
+        } else {
+            word
+        }
+    }
+
+    /// Returns the number of words in the trie.
+    pub fn len(&self) -> usize {
+        self.word_count
+    }
+
+    /// Returns whether the trie contains no words.
+    pub fn is_empty(&self) -> bool {
+        self.word_count == 0
+    }
+
+    /// Returns whether the `word` was inserted into the trie as a complete word.
+    ///
+    /// Unlike [`Trie::find`], this returns `false` for a `word` which is only a prefix of the words
+    /// in the trie.
+    ///
+    /// If the trie has a [`contains_cache`](TrieBuilder::contains_cache), a `word` which is
+    /// rejected by it is known to be absent without descending into the trie.
+    pub fn contains(&self, word: &str) -> bool {
+        let key = self.keys(word);
+        if let Some(cache) = &self.contains_cache {
+            if !cache.may_contain(&key) {
+                return false;
+            }
+        }
+
+        self.root.find(&key).is_some_and(Node::is_word)
+    }
+
+    /// Returns whether any word in the trie starts with the `prefix`, including the `prefix`
+    /// itself.
+    ///
+    /// Unlike [`Trie::contains`], the `prefix` doesn't have to be a complete word. Unlike
+    /// [`Trie::find`], which returns any node on a path in the trie, e.g. the root for an empty
+    /// `prefix`, this requires a word beneath the node of the `prefix`.
+    pub fn starts_with(&self, prefix: &str) -> bool {
+        self.find(prefix).is_some_and(Node::has_words)
+    }
+
+    /// Returns a reference to the value associated with the `word`.
+    ///
+    /// Returns [`None`] if the `word` wasn't inserted as a complete word.
+    pub fn get(&self, word: &str) -> Option<&V> {
+        self.find(word).and_then(Node::data)
+    }
+
+    /// Returns a mutable reference to the value associated with the `word`.
+    ///
+    /// Returns [`None`] if the `word` wasn't inserted as a complete word.
+    pub fn get_mut(&mut self, word: &str) -> Option<&mut V> {
+        let word = self.keys(word);
+        self.root
+            .find_mut(&word)
+            .and_then(|node| node.data.as_mut())
+    }
+
+    /// Returns the longest prefix of the `text` which is a word in the trie.
+    ///
+    /// Returns [`None`] if no word in the trie is a prefix of the `text`. The `text` itself is
+    /// returned if it is a word in the trie.
+    pub fn longest_prefix<'a>(&self, text: &'a str) -> Option<&'a str> {
+        let mut node = &self.root;
+        let mut longest = None;
+        let mut chars = text.char_indices().peekable();
+        while let Some((i, mut key)) = chars.next() {
+            let mut end = i + key.len_utf8();
+            // Like in `Trie::keys`, a letter followed by an accent is matched as the composed
+            // letter, which ends after the accent in the `text`
+            if self.compose_accents {
+                let composed = chars
+                    .peek()
+                    .and_then(|&(_, accent)| compose::compose_pair(key, accent));
+                if let Some(composed) = composed {
+                    key = composed;
+                    end = chars
+                        .next()
+                        .map_or(end, |(j, accent)| j + accent.len_utf8());
+                }
+            }
+            if self.case_insensitive {
+                key = fold_case(key);
+            }
+
+            node = match node.children.get(&key) {
+                Some(child) => child,
+                None => break,
+            };
+
+            if node.is_word() {
+                longest = Some(&text[..end]);
+            }
+        }
+
+        longest
+    }
+
+    /// Returns the longest prefix shared by all the words in the trie, or an empty string if the
+    /// trie is empty.
+    ///
+    /// See [`Trie::longest_common_prefix_with`] to only consider the words with a given prefix.
+    pub fn longest_common_prefix(&self) -> String {
+        self.longest_common_prefix_with("")
+    }
+
+    /// Returns the longest prefix shared by all the words in the trie starting with `prefix`, i.e.
+    /// how far the `prefix` can be completed without choosing between words.
+    ///
+    /// Returns an empty string if no word starts with the `prefix`. In a case-insensitive trie, the
+    /// prefix is returned with the casing of one of the words.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use treeline::Trie;
+    ///
+    /// let history = Trie::from_words(["cargo build --release", "cargo bench", "git status"]);
+    ///
+    /// assert_eq!(history.longest_common_prefix_with("ca"), "cargo b");
+    /// assert_eq!(history.longest_common_prefix_with("g"), "git status");
+    /// assert_eq!(history.longest_common_prefix(), "");
+    /// ```
+    pub fn longest_common_prefix_with(&self, prefix: &str) -> String {
+        let mut node = match self.find(prefix) {
+            Some(node) if node.has_words() => node,
+            _ => return String::new(),
+        };
+
+        // The words diverge at the first node which ends a word, or has several children with
+        // words under them. The children without any, left behind by `Trie::delete_after_prefix`,
+        // are skipped.
+        let mut len = self.keys(prefix).chars().count();
+        while !node.is_word() {
+            let mut children = node.children.values().filter(|child| child.has_words());
+            match (children.next(), children.next()) {
+                (Some(child), None) => node = child,
+                _ => break,
+            }
+            len += 1;
+        }
+
+        // Every word under the node starts with the common prefix
+        node.first_word()
+            .map_or_else(String::new, |word| word.chars().take(len).collect())
+    }
+
+    /// Returns the words in the trie within a [Levenshtein distance] of `max_distance` of the
+    /// `query`, closest words first.
+    ///
+    /// Words at the same distance are in lexicographic order. The trie is searched by computing
+    /// one row of the edit distance matrix per node, so subtrees which can't contain a word
+    /// within `max_distance` are skipped.
+    ///
+    /// [Levenshtein distance]: https://en.wikipedia.org/wiki/Levenshtein_distance
+    pub fn fuzzy_words(&self, query: &str, max_distance: usize) -> Vec<&String> {
+        let query = self.keys(query).chars().collect::<Vec<_>>();
+        let first_row = (0..=query.len()).collect::<Vec<_>>();
+
+        let mut matches = Vec::new();
+        for child in self.root.children.values() {
+            child.fuzzy_words(&query, &first_row, max_distance, &mut matches);
+        }
+
+        matches.sort_by_key(|(distance, _)| *distance);
+        matches.into_iter().map(|(_, word)| word).collect()
+    }
+
+    /// Returns the words in the trie which contain the `needle` anywhere, in lexicographic order.
+    ///
+    /// Unlike [`Trie::words_with_prefix`], the trie can't be used to narrow down the search, so
+    /// every word is examined. An empty `needle` matches every word. In a case-insensitive trie,
+    /// the `needle` matches regardless of its case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use treeline::Trie;
+    ///
+    /// let history = Trie::from_words(["cargo test --release", "cargo build", "git status"]);
+    ///
+    /// assert_eq!(history.words_containing("test"), vec!["cargo test --release"]);
+    /// ```
+    pub fn words_containing(&self, needle: &str) -> Vec<&String> {
+        let needle = self.keys(needle);
+        self.words()
+            .filter(|word| self.keys(word).contains(needle.as_ref()))
+            .collect()
+    }
+
+    /// Returns the words in the trie which match the `pattern`, in lexicographic order.
+    ///
+    /// In the `pattern`, `?` matches exactly one character and `*` matches any sequence of
+    /// characters, including none. Every other character matches itself, or in a
+    /// case-insensitive trie, regardless of its case. Subtrees in which no word can match the
+    /// `pattern` are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use treeline::Trie;
+    ///
+    /// let words = Trie::from_words(["hello", "hallo", "hollow", "help"]);
+    ///
+    /// assert_eq!(words.words_matching("h?llo"), vec!["hallo", "hello"]);
+    /// assert_eq!(words.words_matching("h*w"), vec!["hollow"]);
+    /// ```
+    pub fn words_matching(&self, pattern: &str) -> Vec<&String> {
+        let pattern = self.keys(pattern).chars().collect::<Vec<_>>();
+        let mut positions = Vec::new();
+        push_position(&pattern, 0, &mut positions);
+
+        let mut matches = Vec::new();
+        self.root.words_matching(&pattern, &positions, &mut matches);
+        matches
+    }
+
+    /// Returns the `candidates` which are words in the trie, in their original order.
+    ///
+    /// Candidates whose first character doesn't start any word are skipped without a descent, so
+    /// filtering a batch in which most candidates miss stays cheap.
+    pub fn filter_present<'a>(&self, candidates: &'a [&str]) -> Vec<&'a str> {
+        candidates
+            .iter()
+            .copied()
+            .filter(|candidate| {
+                let first = match self.keys(candidate).chars().next() {
+                    Some(first) => first,
+                    None => return false,
+                };
+
+                self.root.children.contains_key(&first) && self.contains(candidate)
+            })
+            .collect()
+    }
+
+    /// Returns an iterator over the words in the trie with the given prefix, in lexicographic
+    /// order.
+    ///
+    /// If the `prefix` itself is a word, it is yielded first. The empty prefix never is, see
+    /// [`Trie::insert`], so it yields the same words as [`Trie::words`].
+    pub fn words_with_prefix(&self, prefix: &str) -> TrieRead<'_, V> {
+        let stack = self.find(prefix).into_iter().collect::<Vec<_>>();
+
+        TrieRead { stack }
+    }
+
+    /// Returns the `k` most frequently inserted words in the trie with the given prefix, most
+    /// frequent first.
+    ///
+    /// Unlike [`Trie::words_with_prefix`], the `prefix` itself isn't included, as it is no
+    /// completion of itself. Words which were inserted equally often are in lexicographic order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use treeline::Trie;
+    ///
+    /// let mut history = Trie::new();
+    /// history.insert("git status", ());
+    /// history.insert("git push", ());
+    /// history.insert("git status", ());
+    /// history.insert("git log", ());
+    ///
+    /// assert_eq!(history.top_completions("git", 2), vec!["git status", "git log"]);
+    /// ```
+    pub fn top_completions(&self, prefix: &str, k: usize) -> Vec<&String> {
+        let mut completions = Vec::new();
+        let mut stack = match self.find(prefix) {
+            Some(head) => head.children.values().collect::<Vec<_>>(),
+            None => vec![],
+        };
+        while let Some(head) = stack.pop() {
+            stack.extend(head.children.values());
+
+            if head.is_word() {
+                completions.push((head.frequency, &head.value));
+            }
+        }
+
+        completions.sort_unstable_by(|(a_frequency, a), (b_frequency, b)| {
+            b_frequency.cmp(a_frequency).then_with(|| a.cmp(b))
+        });
+        completions.truncate(k);
+        completions.into_iter().map(|(_, word)| word).collect()
+    }
+
+    /// Returns the number of words in the trie with the given prefix.
+    ///
+    /// Like [`Trie::words_with_prefix`], the `prefix` itself is counted if it is a word. The words
+    /// are counted while walking the trie, without collecting them.
+    pub fn count_with_prefix(&self, prefix: &str) -> usize {
+        self.find(prefix).map_or(0, Node::word_count)
+    }
+
+    /// Returns an iterator over all the words in the trie, in lexicographic order.
+    ///
+    /// The words are ordered by their characters, so a word is yielded before the words it is a
+    /// prefix of, e.g. "cargo" before "cargo test".
+    pub fn words(&self) -> TrieRead<'_, V> {
+        TrieRead {
+            stack: self.root.children.values().rev().collect::<Vec<_>>(),
+        }
+    }
+
+    /// Returns an iterator over all the words in the trie, in the order in which they were
+    /// inserted.
+    ///
+    /// Inserting a word which is already present moves it to the end, so the most recently
+    /// inserted word is always yielded last.
+    pub fn words_in_insertion_order(&self) -> impl Iterator<Item = &String> {
+        let mut words = Vec::new();
+        let mut stack = self.root.children.values().collect::<Vec<_>>();
+        while let Some(head) = stack.pop() {
+            stack.extend(head.children.values());
+
+            if head.is_word() {
+                words.push((head.seq, &head.value));
+            }
+        }
+
+        words.sort_unstable_by_key(|(seq, _)| *seq);
+        words.into_iter().map(|(_, word)| word)
+    }
+
+    /// Returns an iterator over all the words in the trie, shortest words first.
+    ///
+    /// Unlike [`Trie::words`], which yields the words depth-first, this iterator traverses the
+    /// trie breadth-first, so the words are yielded in nondecreasing length (in characters). Words
+    /// of equal length are yielded in lexicographic order.
+    pub fn words_bfs(&self) -> TrieReadBfs<'_, V> {
+        TrieReadBfs {
+            queue: self.root.children.values().collect::<VecDeque<_>>(),
+        }
+    }
+
+    /// Returns an iterator over all the nodes in the trie.
+    ///
+    /// For every node, the iterator yields the path from the root to that node, and whether that
+    /// path is a complete word. Unlike [`Trie::words`], this includes the prefixes of the words.
+    /// Like [`Trie::words`], the paths are yielded in lexicographic order.
+    ///
+    /// The paths are built from the keys of the nodes while walking the trie, so in a
+    /// case-insensitive trie their case is folded.
+    pub fn nodes(&self) -> TrieNodes<'_, V> {
+        TrieNodes {
+            stack: self
+                .root
+                .children
+                .values()
+                .rev()
+                .map(|child| (child, child.key.to_string()))
+                .collect::<Vec<_>>(),
+        }
+    }
+
+    /// Returns the number of distinct proper prefixes of the words in the trie.
+    ///
+    /// This is the number of internal nodes, i.e. the nodes which have at least one child.
+    pub fn prefix_count(&self) -> usize {
+        self.root.children.values().map(Node::prefix_count).sum()
+    }
+
+    /// Returns the number of nodes in the trie, excluding the root.
+    ///
+    /// This is the number of distinct non-empty prefixes of the words, including the words
+    /// themselves.
+    pub fn node_count(&self) -> usize {
+        self.root.node_count() - 1
+    }
+
+    /// Returns the length in characters of the longest word in the trie, or 0 if the trie is
+    /// empty.
+    ///
+    /// This is the number of nodes on the longest path from the root to a word, i.e. the most
+    /// nodes a lookup of a word visits. The nodes which aren't part of any word, left behind by
+    /// [`Trie::delete_after_prefix`], aren't counted.
+    pub fn height(&self) -> usize {
+        self.root.height().unwrap_or(0)
+    }
+
+    /// Returns an estimate of the number of bytes the trie takes in memory.
+    ///
+    /// This includes the trie itself, every node with its key and children, and the words stored
+    /// in the nodes, but not the bookkeeping of the maps holding the children, nor any memory owned
+    /// by the values associated with the words.
+    pub fn memory_estimate(&self) -> usize {
+        mem::size_of::<Self>() + self.root.memory_estimate()
+    }
+
+    /// Removes all the words from the trie.
+    ///
+    /// The options the trie was created with, like its [capacity](Trie::with_lru_capacity), are
+    /// kept, so the trie behaves as if it was newly created with them.
+    pub fn clear(&mut self) {
+        self.root.children.clear();
+        self.word_count = 0;
+        self.insertions = 0;
+        self.recency.clear();
+        if let Some(cache) = &mut self.contains_cache {
+            cache.clear();
+        }
+    }
+
+    /// Removes all the words from the trie, and returns an iterator over them, in no particular
+    /// order.
+    ///
+    /// Like [`Vec::drain`], the trie is empty after the call, even if the iterator is dropped before
+    /// all the words were yielded. Like after [`Trie::clear`], the trie behaves as if it was newly
+    /// created.
+    pub fn drain(&mut self) -> impl Iterator<Item = String> {
+        self.word_count = 0;
+        self.insertions = 0;
+        self.recency.clear();
+        if let Some(cache) = &mut self.contains_cache {
+            cache.clear();
+        }
+        let mut stack = mem::take(&mut self.root.children)
+            .into_values()
+            .collect::<Vec<_>>();
+        let mut words = Vec::new();
+        while let Some(node) = stack.pop() {
+            stack.extend(node.children.into_values());
+            if node.data.is_some() {
+                words.push(node.value);
+            }
+        }
+
+        words.into_iter()
+    }
+
+    /// Inserts every word of `other` into this trie, together with its value.
+    ///
+    /// The words are inserted in the order in which they were inserted into `other`, through
+    /// [`Trie::insert`], so the options of this trie apply to them. If a word is present in both
+    /// tries, its value is replaced by the value in `other`, and the frequencies are summed.
+    pub fn merge(&mut self, other: Trie<V>) {
+        let mut stack = vec![other.root];
+        let mut words = Vec::new();
+        while let Some(node) = stack.pop() {
+            stack.extend(node.children.into_values());
+            if let Some(data) = node.data {
+                words.push((node.seq, node.value, data, node.frequency));
+            }
+        }
+
+        words.sort_unstable_by_key(|(seq, _, _, _)| *seq);
+        for (_, word, data, frequency) in words {
+            self.insert_with_frequency(&word, data, frequency);
+        }
+    }
+
+    /// Compares the words in this trie with the words in `other`.
+    ///
+    /// Returns a tuple of two lists: the first contains the words which are only present in this
+    /// trie ("removed"), the second contains the words which are only present in `other`
+    /// ("added"). Words which are present in both tries appear in neither list.
+    pub fn diff<'a>(&'a self, other: &'a Trie<V>) -> (Vec<&'a String>, Vec<&'a String>) {
+        let ours = self.words().collect::<HashSet<_>>();
+        let theirs = other.words().collect::<HashSet<_>>();
+
+        let removed = self.words().filter(|word| !theirs.contains(word)).collect();
+        let added = other.words().filter(|word| !ours.contains(word)).collect();
+
+        (removed, added)
+    }
+
+    /// Writes the words in the trie to `writer`, one word per line, in the order in which they were
+    /// inserted.
+    ///
+    /// The words can be read back with [`Trie::load_from_reader`]. Only the words are written, not
+    /// their values or frequencies, and words containing a newline can't be read back correctly.
+    pub fn write_to_writer(&self, mut writer: impl Write) -> io::Result<()> {
+        for word in self.words_in_insertion_order() {
+            writeln!(writer, "{}", word)?;
+        }
+
+        writer.flush()
+    }
+
+    /// Returns the structure of the trie in the DOT language of Graphviz, to inspect it, e.g. with
+    /// `dot -Tpng`.
+    ///
+    /// Every node is labeled with its key, the nodes which are complete words are drawn as double
+    /// circles. The root is drawn as a point.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph trie {\n    n0 [label=\"\", shape=point];\n");
+        // The nodes are numbered in the order in which they are visited, so the ids are unique even
+        // if the same key appears in multiple places
+        let mut next_id = 1;
+        let mut stack = vec![(0, &self.root)];
+        while let Some((id, head)) = stack.pop() {
+            for child in head.children.values() {
+                let shape = if child.is_word() {
+                    "doublecircle"
+                } else {
+                    "circle"
+                };
+                dot.push_str(&format!(
+                    "    n{} [label=\"{}\", shape={}];\n    n{} -> n{};\n",
+                    next_id,
+                    escape_dot(child.key),
+                    shape,
+                    id,
+                    next_id
+                ));
+                stack.push((next_id, child));
+                next_id += 1;
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl<V: fmt::Debug> fmt::Debug for Trie<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Trie")
+            .field("root", &self.root)
+            .field("word_count", &self.word_count)
+            .field("normalizer", &self.normalizer.as_ref().map(|_| ".."))
+            .field("insertions", &self.insertions)
+            .field("capacity", &self.capacity)
+            .field("clock", &"..")
+            .field("case_insensitive", &self.case_insensitive)
+            .field("compose_accents", &self.compose_accents)
+            .field(
+                "contains_cache",
+                &self.contains_cache.as_ref().map(|_| ".."),
+            )
+            .finish()
+    }
+}
+
+impl<V: PartialEq> PartialEq for Trie<V> {
+    /// Two tries are equal if they contain the same words, with the same values and frequencies,
+    /// regardless of the order in which the words were inserted.
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .word_nodes()
+                .zip(other.word_nodes())
+                .all(|(a, b)| a.value == b.value && a.data == b.data && a.frequency == b.frequency)
+    }
+}
+
+impl<V: Eq> Eq for Trie<V> {}
+
+impl<V> Default for Trie<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: AsRef<str>> FromIterator<S> for Trie {
+    fn from_iter<I: IntoIterator<Item = S>>(words: I) -> Self {
+        Self::from_words(words)
+    }
+}
+
+impl<S: AsRef<str>> Extend<S> for Trie {
+    /// Inserts every word into the trie, see [`Trie::insert`].
+    fn extend<I: IntoIterator<Item = S>>(&mut self, words: I) {
+        for word in words {
+            self.insert(word.as_ref(), ());
+        }
+    }
+}
+
+impl<V> AddAssign<Trie<V>> for Trie<V> {
+    /// Inserts every word of `rhs` into the trie, see [`Trie::merge`].
+    fn add_assign(&mut self, rhs: Trie<V>) {
+        self.merge(rhs);
+    }
+}
+
+impl<'a, V> IntoIterator for &'a Trie<V> {
+    type Item = &'a String;
+    type IntoIter = TrieRead<'a, V>;
+
+    /// Returns an iterator over all the words in the trie, see [`Trie::words`].
+    fn into_iter(self) -> Self::IntoIter {
+        self.words()
+    }
+}
+
+/// Iterator over the words in a [`Trie`]
+///
+/// This iterator is returned from the [`Trie::words_with_prefix`] function on a [`Trie`] and will
+/// yield instances of [`String`].
+pub struct TrieRead<'a, V = ()> {
+    /// Stack to keep track of which [`Node`]s we still need to visit while iterating over the
+    /// words in the trie.
+    stack: Vec<&'a Node<V>>,
+}
+
+impl<'a, V> Iterator for TrieRead<'a, V> {
+    type Item = &'a String;
+
+    // Iterates over the words in the trie using depth-first search
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(head) = self.stack.pop() {
+            // Store the children on the stack, to be examined later. They are pushed in reverse,
+            // so the smallest child is popped first, which yields the words in lexicographic order
+            for child in head.children.values().rev() {
+                self.stack.push(child);
+            }
+
+            // If a node is the end of a word, we should return the value, since that will contain
+            // a complete word. Otherwise, we don't return here, but simply continue looping until
+            // we either reach a node containing a complete word, or we run out of nodes.
+            if head.is_word() {
+                return Some(&head.value);
+            }
+        }
+
+        None
+    }
+}
+
+/// Breadth-first iterator over the words in a [`Trie`]
+///
+/// This iterator is returned from the [`Trie::words_bfs`] function on a [`Trie`] and will yield
+/// instances of [`String`], shortest first.
+pub struct TrieReadBfs<'a, V = ()> {
+    /// Queue to keep track of which [`Node`]s we still need to visit while iterating over the
+    /// words in the trie.
+    queue: VecDeque<&'a Node<V>>,
+}
+
+impl<'a, V> Iterator for TrieReadBfs<'a, V> {
+    type Item = &'a String;
+
+    // Iterates over the words in the trie using breadth-first search
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(head) = self.queue.pop_front() {
+            // Store the children at the back of the queue, so all nodes at the current depth are
+            // examined before them
+            for child in head.children.values() {
+                self.queue.push_back(child);
+            }
+
+            if head.is_word() {
+                return Some(&head.value);
+            }
+        }
+
+        None
+    }
+}
+
+/// Iterator over the nodes in a [`Trie`]
+///
+/// This iterator is returned from the [`Trie::nodes`] function on a [`Trie`] and will yield the
+/// path to every node, together with whether that path is a complete word.
+pub struct TrieNodes<'a, V = ()> {
+    /// Stack to keep track of which [`Node`]s we still need to visit, together with the path from
+    /// the root to them.
+    stack: Vec<(&'a Node<V>, String)>,
+}
+
+impl<'a, V> Iterator for TrieNodes<'a, V> {
+    type Item = (String, bool);
+
+    // Iterates over the nodes in the trie using depth-first search
+    fn next(&mut self) -> Option<Self::Item> {
+        let (head, path) = self.stack.pop()?;
+        for child in head.children.values().rev() {
+            self.stack.push((child, format!("{}{}", path, child.key)));
+        }
+
+        Some((path, head.is_word()))
+    }
+}
+
+/// A `Node` in a [`Trie`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Node<V = ()> {
+    /// The last character of the path from the root to this node, with its case folded if the trie
+    /// is case-insensitive. This is the key of the node in the `children` of its parent.
+    key: char,
+    /// The complete word ending at this node, or an empty string if the node is only a prefix of
+    /// other words.
+    ///
+    /// Only the nodes ending a word store it, storing the path in every node would take memory
+    /// quadratic in the length of the words. The paths of the other nodes are built from the keys
+    /// while walking the trie.
+    value: String,
+    /// The children, i.e. the nodes whose path has the path to this node as a prefix.
+    children: BTreeMap<char, Node<V>>,
+    /// The value associated with `value` if it is a complete word, or [`None`] if it is only a
+    /// prefix of other words.
+    data: Option<V>,
+    /// The number of insertions which ended at this node, see [`Trie::top_completions`].
+    frequency: u64,
+    /// The number of the last insertion which ended at this node, see [`Trie::insertions`].
+    seq: Option<u64>,
+    /// The time of the last insertion which ended at this node, see [`Trie::expire`].
+    touched: Option<SystemTime>,
+}
+
+impl<V> Node<V> {
+    /// Creates a new `Node` with the given key, which isn't a word yet.
+    fn new(key: char) -> Self {
+        Self {
+            key,
+            value: String::new(),
+            children: BTreeMap::new(),
+            data: None,
+            frequency: 0,
+            seq: None,
+            touched: None,
+        }
+    }
+
+    /// Inserts the `word` under the current node.
+    ///
+    /// If a part of the `word` is not yet present under the current node, that part is added. The
+    /// already existing part of the `word` is unchanged. Returns the node containing the last
+    /// character of the `word`, which is left to the caller to associate a value with.
+    ///
+    /// If `case_insensitive` is set, the nodes are keyed by the folded case of the characters.
+    fn insert(&mut self, word: &str, case_insensitive: bool) -> &mut Self {
+        if let Some(root) = word.chars().next() {
+            let key = if case_insensitive {
+                fold_case(root)
+            } else {
+                root
+            };
+            let child = self.children.entry(key).or_insert_with(|| Node::new(key));
+            return child.insert(&word[root.len_utf8()..], case_insensitive);
+        }
+
+        self
+    }
+
+    /// Deletes the word under the current node.
+    ///
+    /// Only the part that is not part of another word will be removed, if part of the word is a
+    /// prefix of another word under the current node, or is a word itself, that part will not be
+    /// removed. Returns whether the word was present.
+    fn delete(&mut self, word: &str) -> bool {
+        match word.chars().next() {
+            Some(root) => {
+                if let Some(child) = self.children.get_mut(&root) {
+                    // Firstly, try to delete the remainder of the word
+                    let deleted = child.delete(&word[root.len_utf8()..]);
+
+                    // Secondly, if `child` is no longer part of any word, it can be safely
+                    // removed. This can be the case when there are a few nodes with only 1 child,
+                    // this takes care that we remove them recursively.
+                    if !child.is_word() && child.children.is_empty() {
+                        self.children.remove(&root);
+                    }
+
+                    deleted
+                } else {
+                    false
+                }
+            }
+            None => self.unmark_word(),
+        }
+    }
+
+    /// Makes the current node no longer a word, keeping its children. Returns whether it was a
+    /// word.
+    fn unmark_word(&mut self) -> bool {
+        self.value = String::new();
+        self.frequency = 0;
+        self.seq = None;
+        self.touched = None;
+        self.data.take().is_some()
+    }
+
+    /// Deletes the words under the current node for which `keep` returns `false`.
+    ///
+    /// Like [`Node::delete`], the nodes which are no longer part of any word are removed. Returns
+    /// the number of deleted words.
+    fn retain<F: FnMut(&str) -> bool>(&mut self, keep: &mut F) -> usize {
+        let mut removed = 0;
+        if self.is_word() && !keep(&self.value) {
+            self.unmark_word();
+            removed += 1;
+        }
+
+        self.children.retain(|_, child| {
+            removed += child.retain(keep);
+            child.is_word() || !child.children.is_empty()
+        });
+
+        removed
+    }
+
+    /// Deletes the words under the current node starting with the `prefix`.
+    ///
+    /// Like [`Node::delete`], the nodes which are no longer part of any word are removed. Returns
+    /// the number of deleted words.
+    fn remove_prefix(&mut self, prefix: &str) -> usize {
+        match prefix.chars().next() {
+            Some(root) => match self.children.get_mut(&root) {
+                Some(child) => {
+                    let removed = child.remove_prefix(&prefix[root.len_utf8()..]);
+                    if !child.is_word() && child.children.is_empty() {
+                        self.children.remove(&root);
+                    }
+
+                    removed
+                }
+                None => 0,
+            },
+            None => {
+                let removed = self.word_count();
+                self.children.clear();
+                self.unmark_word();
+                removed
+            }
+        }
+    }
+
+    /// Returns the last character of the word stored in this node.
+    ///
+    /// In a case-insensitive trie, this is the character with its case folded.
+    pub fn key(&self) -> char {
+        self.key
+    }
+
+    /// Returns whether the word stored in this node is a complete word, rather than only a prefix
+    /// of other words.
+    pub fn is_word(&self) -> bool {
+        self.data.is_some()
+    }
+
+    /// Returns the number of times the word stored in this node was inserted since it was last
+    /// deleted, which is 0 if it is only a prefix of other words.
+    pub fn frequency(&self) -> u64 {
+        self.frequency
+    }
+
+    /// Returns the value associated with the word stored in this node, or [`None`] if it is only a
+    /// prefix of other words.
+    pub fn data(&self) -> Option<&V> {
+        self.data.as_ref()
+    }
+
+    /// Returns the number of nodes under and including the current node.
+    fn node_count(&self) -> usize {
+        1 + self.children.values().map(Node::node_count).sum::<usize>()
+    }
+
+    /// Returns the number of nodes on the longest path from the current node to a word, excluding
+    /// the current node, or [`None`] if there is no word under or at the current node.
+    fn height(&self) -> Option<usize> {
+        let below = self
+            .children
+            .values()
+            .filter_map(|child| Some(1 + child.height()?))
+            .max();
+
+        below.or_else(|| Some(0).filter(|_| self.is_word()))
+    }
+
+    /// Returns the number of bytes owned by the current node, including the nodes under it, see
+    /// [`Trie::memory_estimate`].
+    fn memory_estimate(&self) -> usize {
+        let children = self
+            .children
+            .values()
+            .map(|child| mem::size_of::<(char, Self)>() + child.memory_estimate())
+            .sum::<usize>();
+        self.value.capacity() + children
+    }
+
+    /// Returns the number of nodes under and including the current node which have children.
+    fn prefix_count(&self) -> usize {
+        if self.children.is_empty() {
+            0
+        } else {
+            1 + self
+                .children
+                .values()
+                .map(Node::prefix_count)
+                .sum::<usize>()
+        }
+    }
+
+    /// Returns the first word under or at the current node, in lexicographic order.
+    fn first_word(&self) -> Option<&String> {
+        if self.is_word() {
+            return Some(&self.value);
+        }
+
+        self.children.values().find_map(Node::first_word)
+    }
+
+    /// Returns whether there is a word under or at the current node.
+    fn has_words(&self) -> bool {
+        self.is_word() || self.children.values().any(Node::has_words)
+    }
+
+    /// Returns the number of words under and including the current node.
+    fn word_count(&self) -> usize {
+        usize::from(self.is_word()) + self.children.values().map(Node::word_count).sum::<usize>()
+    }
+
+    /// Collects the words under and including the current node within `max_distance` of the
+    /// `query` into `matches`, together with their distance.
+    ///
+    /// The `previous_row` is the row of the edit distance matrix of the parent node, i.e. the
+    /// distances between the parent's word and every prefix of the `query`.
+    fn fuzzy_words<'a>(
+        &'a self,
+        query: &[char],
+        previous_row: &[usize],
+        max_distance: usize,
+        matches: &mut Vec<(usize, &'a String)>,
+    ) {
+        let mut row = Vec::with_capacity(previous_row.len());
+        row.push(previous_row[0] + 1);
+        for (i, c) in query.iter().enumerate() {
+            let substitution = previous_row[i] + usize::from(*c != self.key);
+            let insertion = row[i] + 1;
+            let deletion = previous_row[i + 1] + 1;
+            row.push(substitution.min(insertion).min(deletion));
+        }
+
+        let distance = row[query.len()];
+        if self.is_word() && distance <= max_distance {
+            matches.push((distance, &self.value));
+        }
+
+        // The distances only grow further down the trie, so if no prefix of the `query` is within
+        // `max_distance`, none of the words below this node can be either
+        if row.iter().min().is_some_and(|&min| min <= max_distance) {
+            for child in self.children.values() {
+                child.fuzzy_words(query, &row, max_distance, matches);
+            }
+        }
+    }
+
+    /// Collects the words under and including the current node which match the `pattern` into
+    /// `matches`.
+    ///
+    /// The `positions` are the positions in the `pattern` up to which the path to the current
+    /// node matches. A `*` may match any number of characters, so a path can match up to several
+    /// positions at once.
+    fn words_matching<'a>(
+        &'a self,
+        pattern: &[char],
+        positions: &[usize],
+        matches: &mut Vec<&'a String>,
+    ) {
+        if self.is_word() && positions.contains(&pattern.len()) {
+            matches.push(&self.value);
+        }
+
+        for child in self.children.values() {
+            let mut next = Vec::new();
+            for &position in positions {
+                match pattern.get(position) {
+                    // The `*` matches the key of `child` and stays at the same position
+                    Some('*') => push_position(pattern, position, &mut next),
+                    Some(&c) if c == '?' || c == child.key => {
+                        push_position(pattern, position + 1, &mut next)
+                    }
+                    _ => {}
+                }
+            }
+
+            // If the path to `child` matches no position, no word below it can match either
+            if !next.is_empty() {
+                child.words_matching(pattern, &next, matches);
+            }
+        }
+    }
+
+    /// Returns a reference to the [`Node`] containing the last character of the `word`.
+    ///
+    /// The `word` is matched against the [keys](Node::key) as is, so in a case-insensitive trie
+    /// its case should already be folded.
+    pub fn find(&self, word: &str) -> Option<&Self> {
+        if let Some(root) = word.chars().next() {
+            if let Some(child) = self.children.get(&root) {
+                return child.find(&word[root.len_utf8()..]);
+            } else {
+                return None;
+            }
+        }
+
+        Some(self)
+    }
+
+    /// Returns a mutable reference to the [`Node`] containing the last character of the `word`.
+    fn find_mut(&mut self, word: &str) -> Option<&mut Self> {
+        if let Some(root) = word.chars().next() {
+            if let Some(child) = self.children.get_mut(&root) {
+                return child.find_mut(&word[root.len_utf8()..]);
+            } else {
+                return None;
+            }
+        }
+
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Node, StringTrie, Trie};
+    use std::{
+        io::Cursor,
+        mem,
+        sync::{Arc, Mutex},
+        time::{Duration, SystemTime},
+    };
+
+    #[test]
+    fn insert_single() {
+        let mut trie = Trie::new();
+        let input = "Hello world!";
+
+        trie.insert(input, ());
+        assert!(trie.find(input).is_some());
+        assert!(trie.find("Hi there").is_none());
+    }
+
+    #[test]
+    fn insert_multiple() {
+        let mut trie = Trie::new();
+        let input1 = "Hello world!";
+        let input2 = "Hello sir!";
+        let input3 = "Good afternoon!";
+
+        trie.insert(input1, ());
+        trie.insert(input2, ());
+        trie.insert(input3, ());
+
+        assert!(trie.find(input1).is_some());
+        assert!(trie.find(input2).is_some());
+        assert!(trie.find(input3).is_some());
+        assert!(trie.find("Hi there").is_none());
+    }
+
+    #[test]
+    fn delete_single() {
+        let mut trie = Trie::new();
+        let input = "Hello world!";
+
+        trie.insert(input, ());
+        assert!(trie.find(input).is_some());
+
+        assert!(trie.delete(input));
+        assert!(trie.find(input).is_none());
+        assert!(!trie.delete(input));
+
+        assert_eq!(trie.len(), 0);
+    }
+
+    #[test]
+    fn delete_prefix() {
+        let mut trie = Trie::new();
+        let input = "Hello world!";
+
+        trie.insert(input, ());
+        assert!(trie.find(input).is_some());
+        let node_count = trie.node_count();
+
+        assert!(!trie.delete("Hello"));
+        assert!(trie.find(input).is_some());
+        assert_eq!(trie.node_count(), node_count);
+        assert!(!trie.delete("Goedemorgen"));
+
+        assert_eq!(trie.len(), 1);
+    }
+
+    #[test]
+    fn retain() {
+        let mut trie =
+            Trie::from_words(["cargo", "cargo test", "cargo build", "git", "git status"]);
+        let mut seen = Vec::new();
+        trie.retain(|word| {
+            seen.push(word.to_string());
+            word.contains(' ')
+        });
+
+        assert_eq!(
+            seen,
+            vec!["cargo", "cargo build", "cargo test", "git", "git status"]
+        );
+        assert_eq!(trie.len(), 3);
+        assert_eq!(
+            trie.words().collect::<Vec<_>>(),
+            vec!["cargo build", "cargo test", "git status"]
+        );
+        // The prefixes of the kept words are still there, but are no longer words
+        assert!(trie.starts_with("cargo"));
+        assert!(!trie.contains("cargo"));
+        assert!(!trie.contains("git"));
+    }
+
+    #[test]
+    fn retain_collapses_branches() {
+        let mut trie = Trie::from_words(["git", "git status", "git stash"]);
+        let node_count = trie.node_count();
+        trie.retain(|word| word != "git stash");
+
+        // "git sta" is shared with "git status", only "sh" is removed
+        assert_eq!(trie.node_count(), node_count - 2);
+        assert!(!trie.starts_with("git stas"));
+        assert_eq!(trie.len(), 2);
+
+        trie.retain(|_| false);
+        assert!(trie.is_empty());
+        assert_eq!(trie.node_count(), 0);
+    }
+
+    #[test]
+    fn delete_after_prefix() {
+        let mut trie = Trie::new();
+        let input = "Hello world!";
+
+        trie.insert(input, ());
+        assert!(trie.find(input).is_some());
+
+        trie.delete_after_prefix("Hello ", "world!");
+        assert!(trie.find(input).is_none());
+        assert!(trie.find("Hello ").is_some());
+
+        assert_eq!(trie.len(), 0);
+    }
+
+    #[test]
+    fn overlapping_words() {
+        let mut trie = Trie::new();
+        trie.insert("Hello world", ());
+        trie.insert("Hello", ());
+        trie.insert("Hello sir", ());
+
+        let mut words = trie.words().collect::<Vec<_>>();
+        words.sort_unstable();
+        assert_eq!(words, vec!["Hello", "Hello sir", "Hello world"]);
+        assert!(trie.find("Hello").unwrap().is_word());
+        assert!(!trie.find("Hello ").unwrap().is_word());
+
+        // Deleting the shorter word keeps the longer ones, and the other way around
+        trie.delete("Hello");
+        assert!(!trie.find("Hello").unwrap().is_word());
+        assert_eq!(trie.len(), 2);
+
+        trie.insert("Hello", ());
+        trie.delete("Hello world");
+        trie.delete("Hello sir");
+        assert_eq!(trie.words().collect::<Vec<_>>(), vec!["Hello"]);
+        assert!(trie.find("Hello ").is_none());
+    }
+
+    #[test]
+    fn prefix_is_not_a_word() {
+        let mut trie = Trie::new();
+        trie.insert("Hello world", ());
+
+        assert!(!trie.find("Hello").unwrap().is_word());
+        assert!(trie.find("Hello world").unwrap().is_word());
+
+        // Deleting a word which isn't present leaves the trie unchanged
+        trie.delete("Hello");
+        trie.delete("Hello world and goodbye");
+        assert_eq!(trie.words().collect::<Vec<_>>(), vec!["Hello world"]);
+    }
+
+    #[test]
+    fn overlapping_words_order() {
+        let mut trie = Trie::new();
+        trie.insert("cargo test --release", ());
+        trie.insert("cargo", ());
+        trie.insert("cargo test", ());
+
+        assert_eq!(
+            trie.words_bfs().collect::<Vec<_>>(),
+            vec!["cargo", "cargo test", "cargo test --release"]
+        );
+        assert_eq!(
+            trie.words_in_insertion_order().collect::<Vec<_>>(),
+            vec!["cargo test --release", "cargo", "cargo test"]
+        );
+    }
+
+    #[test]
+    fn delete_multiple() {
+        let mut trie = Trie::new();
+        let input1 = "Hello world!";
+        let input2 = "Hello sir!";
+        let input3 = "Good afternoon!";
+
+        trie.insert(input1, ());
+        trie.insert(input2, ());
+        trie.insert(input3, ());
+
+        assert!(trie.find(input1).is_some());
+        assert!(trie.find(input2).is_some());
+        assert!(trie.find(input3).is_some());
+
+        trie.delete(input1);
+        assert!(trie.find(input1).is_none());
+
+        trie.delete(input3);
+        assert!(trie.find(input3).is_none());
+
+        assert_eq!(trie.len(), 1);
+    }
+
+    #[test]
+    fn find_in_empty_trie() {
+        let trie = StringTrie::new();
+
+        assert!(trie.find(" ").is_none());
+    }
+
+    #[test]
+    fn find_prefix() {
+        let mut trie = Trie::new();
+        trie.insert("Hello world!", ());
+
+        assert!(trie.find("Hello").is_some());
+    }
+
+    #[test]
+    fn find_from_prefix() {
+        let mut trie = Trie::new();
+        trie.insert("Hello world!", ());
+        trie.insert("Hello sir!", ());
+        trie.insert("Hello miss!", ());
+
+        if let Some(node) = trie.find("Hello ") {
+            assert!(node.find("sir").is_some());
+        }
+    }
+
+    #[test]
+    fn contains() {
+        let mut trie = Trie::new();
+        trie.insert("Hello world", ());
+
+        assert!(trie.contains("Hello world"));
+        assert!(!trie.contains("Hello"));
+        assert!(!trie.contains("Goedemorgen"));
+        assert!(!trie.contains(""));
+
+        trie.insert("Hello", ());
+        assert!(trie.contains("Hello"));
+    }
+
+    #[test]
+    fn starts_with() {
+        let mut trie = Trie::new();
+        assert!(!trie.starts_with(""));
+        assert!(trie.find("").is_some());
+
+        trie.insert("cargo test", ());
+        trie.insert("cargo", ());
+
+        assert!(trie.starts_with("car"));
+        assert!(!trie.contains("car"));
+        assert!(trie.starts_with("cargo"));
+        assert!(trie.contains("cargo"));
+        assert!(trie.starts_with("cargo test"));
+        assert!(!trie.starts_with("cargo tests"));
+        assert!(!trie.starts_with("git"));
+        assert!(trie.starts_with(""));
+
+        trie.delete("cargo test");
+        assert!(!trie.starts_with("cargo "));
+        assert!(trie.starts_with("cargo"));
+    }
+
+    #[test]
+    fn len() {
+        let mut trie = Trie::new();
+        assert!(trie.is_empty());
+
+        trie.insert("Hello world", ());
+        trie.insert("Hello world", ());
+        assert_eq!(trie.len(), 1);
+
+        trie.insert("Hello", ());
+        assert_eq!(trie.len(), 2);
+        assert!(!trie.is_empty());
+
+        // Deleting a prefix which isn't a word, or an absent word, doesn't change the count
+        trie.delete("Hello ");
+        trie.delete("Goedemorgen");
+        assert_eq!(trie.len(), 2);
+
+        trie.delete("Hello");
+        trie.delete("Hello");
+        assert_eq!(trie.len(), 1);
+
+        trie.insert("Hello sir", ());
+        trie.delete_after_prefix("Hello ", "world");
+        assert_eq!(trie.len(), 1);
+        assert!(!trie.contains("Hello "));
+
+        assert_eq!(trie.drain().count(), 1);
+        assert!(trie.is_empty());
+    }
+
+    #[test]
+    fn empty_word() {
+        let mut trie = Trie::new();
+        assert!(!trie.insert("", ()));
+        assert!(trie.insert("a", ()));
+        assert!(!trie.contains(""));
+        assert_eq!(trie.len(), 1);
+        assert_eq!(trie.len(), trie.words().count());
+
+        // A word normalized to the empty string is rejected as well
+        let mut trie = Trie::with_normalizer(|word| Some(word.trim().to_string()));
+        assert!(!trie.insert("  ", ()));
+        assert!(trie.is_empty());
+
+        // Round trips keep every word
+        let trie = ["", "a", "ab"].iter().copied().collect::<Trie>();
+        assert_eq!(trie.len(), trie.words().count());
+        assert_eq!(trie.words().map(String::as_str).collect::<Trie>(), trie);
+    }
+
+    #[test]
+    #[should_panic(expected = "the empty string can't be inserted")]
+    fn get_or_insert_with_empty_word() {
+        Trie::new().get_or_insert_with("", || 0);
+    }
+
+    #[test]
+    fn count_with_prefix() {
+        let mut trie = Trie::new();
+        trie.insert("cargo", ());
+        trie.insert("cargo build", ());
+        trie.insert("cargo test", ());
+        trie.insert("git status", ());
+
+        assert_eq!(trie.count_with_prefix("cargo"), 3);
+        assert_eq!(trie.count_with_prefix("cargo "), 2);
+        assert_eq!(trie.count_with_prefix("cargo test"), 1);
+        assert_eq!(trie.count_with_prefix(""), 4);
+        assert_eq!(trie.count_with_prefix("ls"), 0);
+    }
+
+    #[test]
+    fn words_with_prefix_includes_prefix_word() {
+        let mut trie = Trie::new();
+        trie.insert("goto", ());
+        trie.insert("go", ());
+        trie.insert("gopher", ());
+
+        assert_eq!(
+            trie.words_with_prefix("go").collect::<Vec<_>>(),
+            vec!["go", "gopher", "goto"]
+        );
+        assert_eq!(
+            trie.words_with_prefix("goto").collect::<Vec<_>>(),
+            vec!["goto"]
+        );
+    }
+
+    #[test]
+    fn words_with_prefix_excludes_prefix_non_word() {
+        let mut trie = Trie::new();
+        trie.insert("goto", ());
+        trie.insert("gopher", ());
+
+        assert_eq!(
+            trie.words_with_prefix("go").collect::<Vec<_>>(),
+            vec!["gopher", "goto"]
+        );
+
+        // The empty prefix is never a word, so it yields the same words as `Trie::words`
+        trie.insert("", ());
+        assert_eq!(
+            trie.words_with_prefix("").collect::<Vec<_>>(),
+            trie.words().collect::<Vec<_>>()
+        );
+        assert_eq!(trie.words_with_prefix("").count(), trie.len());
+    }
+
+    #[test]
+    fn count_with_prefix_matches_collect() {
+        let mut trie = Trie::new();
+        for i in 0..2000 {
+            trie.insert(&format!("cargo test {}", i), ());
+            trie.insert(&format!("git log -{}", i), ());
+        }
+
+        let prefix = "cargo test 1";
+        let collected = trie.words_with_prefix(prefix).collect::<Vec<_>>().len();
+        let counted = trie.count_with_prefix(prefix);
+
+        assert_eq!(counted, collected);
+        assert_eq!(counted, 1111);
+    }
+
+    #[test]
+    fn get() {
+        let mut trie = Trie::new();
+        trie.insert("cargo build", 1);
+        trie.insert("cargo test", 2);
+
+        assert_eq!(trie.get("cargo build"), Some(&1));
+        assert_eq!(trie.get("cargo test"), Some(&2));
+        assert_eq!(trie.get("cargo"), None);
+        assert_eq!(trie.get("git status"), None);
+
+        *trie.get_mut("cargo build").unwrap() += 10;
+        assert_eq!(trie.get("cargo build"), Some(&11));
+        assert!(trie.get_mut("cargo").is_none());
+
+        trie.delete("cargo build");
+        assert_eq!(trie.get("cargo build"), None);
+    }
+
+    #[test]
+    fn insert_overwrites_value() {
+        let mut trie = Trie::new();
+        trie.insert("cargo", "first");
+        trie.insert("cargo test", "test");
+        trie.insert("cargo", "second");
+
+        assert_eq!(trie.len(), 2);
+        assert_eq!(trie.get("cargo"), Some(&"second"));
+        assert_eq!(
+            trie.find("cargo").and_then(|node| node.data()),
+            Some(&"second")
+        );
+        assert_eq!(trie.get("cargo test"), Some(&"test"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let mut trie = Trie::new();
+        trie.insert("cargo", 1);
+        trie.insert("cargo test", 2);
+        trie.insert("cargo test --release", 3);
+        trie.insert("git status", 4);
+
+        let json = serde_json::to_string(&trie).unwrap();
+        let restored: Trie<i32> = serde_json::from_str(&json).unwrap();
+
+        let mut words = trie.words().collect::<Vec<_>>();
+        let mut restored_words = restored.words().collect::<Vec<_>>();
+        words.sort_unstable();
+        restored_words.sort_unstable();
+        assert_eq!(restored_words, words);
+
+        assert_eq!(restored.len(), 4);
+        assert_eq!(restored.get("cargo test"), Some(&2));
+        assert!(restored.find("cargo te").is_some());
+        assert!(!restored.contains("cargo te"));
+        assert!(restored.find("git push").is_none());
+        assert_eq!(
+            restored.words_in_insertion_order().collect::<Vec<_>>(),
+            trie.words_in_insertion_order().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn write_and_load_round_trip() {
+        let mut trie = Trie::new();
+        trie.insert("git status", ());
+        trie.insert("cargo build", ());
+        trie.insert("漢字", ());
+
+        let mut buffer = Vec::new();
+        trie.write_to_writer(&mut buffer).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer.clone()).unwrap(),
+            "git status\ncargo build\n漢字\n"
+        );
+
+        let loaded = Trie::load_from_reader(buffer.as_slice()).unwrap();
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(
+            loaded.words_in_insertion_order().collect::<Vec<_>>(),
+            trie.words_in_insertion_order().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn load_empty_reader() {
+        let trie = Trie::load_from_reader("\n\n".as_bytes()).unwrap();
+
+        assert!(trie.is_empty());
+    }
+
+    #[test]
+    fn insert_lines() {
+        let mut trie = Trie::new();
+        trie.insert("ls", ());
+
+        let input = "cargo build\r\ncargo test\n\ngit status\ncargo build\n漢字";
+        let inserted = trie.insert_lines(Cursor::new(input)).unwrap();
+
+        assert_eq!(inserted, 5);
+        assert_eq!(trie.len(), 5);
+        assert_eq!(
+            trie.words().collect::<Vec<_>>(),
+            vec!["cargo build", "cargo test", "git status", "ls", "漢字"]
+        );
+
+        let mut trie = Trie::builder()
+            .normalizer(|word| Some(word.trim().to_string()).filter(|word| !word.is_empty()))
+            .build();
+        assert_eq!(trie.insert_lines(Cursor::new("  \n git \n")).unwrap(), 1);
+        assert_eq!(trie.words().collect::<Vec<_>>(), vec!["git"]);
+    }
+
+    #[test]
+    fn clone_is_independent() {
+        let mut trie = Trie::new();
+        trie.insert("cargo build", 1);
+        trie.insert("cargo test", 2);
+
+        let mut snapshot = trie.clone();
+        snapshot.delete("cargo test");
+        *snapshot.get_mut("cargo build").unwrap() += 1;
+        snapshot.insert("git status", 3);
+
+        assert_eq!(trie.len(), 2);
+        assert_eq!(trie.get("cargo build"), Some(&1));
+        assert_eq!(trie.get("cargo test"), Some(&2));
+        assert!(!trie.contains("git status"));
+        assert_eq!(
+            snapshot.words().collect::<Vec<_>>(),
+            vec!["cargo build", "git status"]
+        );
+    }
+
+    #[test]
+    fn eq_regardless_of_insertion_order() {
+        let a = Trie::from_words(["cargo", "cargo test", "git status"]);
+        let b = Trie::from_words(["git status", "cargo test", "cargo"]);
+        assert_eq!(a, b);
+        assert_eq!(b, a);
+
+        // The prefix "cargo" of "cargo test" isn't a word in `c`
+        let c = Trie::from_words(["git status", "cargo test"]);
+        assert_ne!(a, c);
+        assert_ne!(c, a);
+
+        let d = Trie::from_words(["cargo", "cargo test", "git stash"]);
+        assert_ne!(a, d);
+        assert_ne!(d, a);
+    }
+
+    #[test]
+    fn eq_compares_values_and_frequencies() {
+        let mut a = Trie::new();
+        a.insert("cargo", 1);
+        let mut b = Trie::new();
+        b.insert("cargo", 2);
+        assert_ne!(a, b);
+
+        b.insert("cargo", 1);
+        assert_ne!(a, b);
+
+        a.insert("cargo", 1);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn to_dot() {
+        let trie = Trie::from_words(["ab", "ac", "a\"b"]);
+        let dot = trie.to_dot();
+
+        assert!(dot.starts_with("digraph trie {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert_eq!(dot.matches("label=").count(), 6);
+        assert_eq!(dot.matches(" -> ").count(), 5);
+        assert_eq!(dot.matches("shape=doublecircle").count(), 3);
+        assert!(dot.contains("    n1 [label=\"a\", shape=circle];\n    n0 -> n1;\n"));
+        assert!(dot.contains("[label=\"\\\"\", shape=circle];\n    n1 -> n2;\n"));
+        // The key "b" appears twice, as different nodes
+        assert_eq!(dot.matches("[label=\"b\", shape=doublecircle]").count(), 2);
+        assert!(dot.contains("    n5 [label=\"b\", shape=doublecircle];\n    n2 -> n5;\n"));
+    }
+
+    #[test]
+    fn diff_overlapping() {
+        let mut old = Trie::new();
+        old.insert("cargo build", ());
+        old.insert("cargo test", ());
+        old.insert("git status", ());
+
+        let mut new = Trie::new();
+        new.insert("cargo test", ());
+        new.insert("git status", ());
+        new.insert("git push", ());
+
+        let (removed, added) = old.diff(&new);
+        assert_eq!(removed, vec!["cargo build"]);
+        assert_eq!(added, vec!["git push"]);
+    }
+
+    #[test]
+    fn diff_identical() {
+        let mut old = Trie::new();
+        old.insert("Hello world!", ());
+        old.insert("Hello sir!", ());
+
+        let mut new = Trie::new();
+        new.insert("Hello sir!", ());
+        new.insert("Hello world!", ());
+
+        let (removed, added) = old.diff(&new);
+        assert!(removed.is_empty());
+        assert!(added.is_empty());
+    }
+
+    #[test]
+    fn drain() {
+        let mut trie = Trie::new();
+        trie.insert("Hello world!", ());
+        trie.insert("Hello sir!", ());
+        trie.insert("Goedemorgen", ());
+        trie.insert("Hello", ());
+
+        let mut drained = trie.drain().collect::<Vec<_>>();
+        drained.sort_unstable();
+        assert_eq!(
+            drained,
+            vec!["Goedemorgen", "Hello", "Hello sir!", "Hello world!"]
+        );
+        assert_eq!(trie.len(), 0);
+        assert_eq!(trie.words().next(), None);
+        assert_eq!(trie.node_count(), 0);
+
+        // Dropping the iterator early still empties the trie
+        trie.insert("Hello world!", ());
+        trie.insert("Goedemorgen", ());
+        assert!(trie.drain().next().is_some());
+        assert_eq!(trie.len(), 0);
+        assert!(trie.find("Hello").is_none());
+    }
+
+    #[test]
+    fn clear() {
+        let mut trie = Trie::with_lru_capacity(2);
+        trie.insert("Hello world!", ());
+        trie.insert("Hello sir!", ());
+
+        trie.clear();
+        assert!(trie.is_empty());
+        assert_eq!(trie.words().next(), None);
+        assert!(trie.find("Hello").is_none());
+
+        // The trie behaves like a new one, including its capacity
+        trie.insert("Goedemorgen", ());
+        trie.insert("Hello world!", ());
+        trie.insert("Hello", ());
+        assert_eq!(trie.len(), 2);
+        assert_eq!(
+            trie.words_in_insertion_order().collect::<Vec<_>>(),
+            vec!["Hello world!", "Hello"]
+        );
+    }
+
+    #[test]
+    fn from_iter() {
+        let words = vec!["cargo", "cargo test", "git status", "cargo"];
+        let trie = words.clone().into_iter().collect::<Trie>();
+        assert_eq!(trie.len(), 3);
+        assert!(words.iter().all(|word| trie.contains(word)));
+
+        let words = vec!["cargo".to_string(), "cargo build".to_string()];
+        let trie: Trie = words.clone().into_iter().collect();
+        assert_eq!(trie.len(), 2);
+        assert!(trie.words().all(|word| words.contains(word)));
+    }
+
+    #[test]
+    fn from_words() {
+        let words = ["cargo", "cargo test", "git status", "cargo"];
+        let trie = Trie::from_words(words);
+
+        assert_eq!(trie.len(), 3);
+        assert!(words.iter().all(|word| trie.contains(word)));
+        assert!(!trie.contains("git"));
+    }
+
+    #[test]
+    fn extend() {
+        let mut trie = Trie::default();
+        trie.insert("cargo", ());
+        trie.extend(vec!["cargo test", "cargo"]);
+        trie.extend(vec!["git status".to_string()]);
+
+        let mut words = trie.words().collect::<Vec<_>>();
+        words.sort_unstable();
+        assert_eq!(words, vec!["cargo", "cargo test", "git status"]);
+        assert_eq!(trie.len(), 3);
+    }
+
+    #[test]
+    fn into_iter() {
+        let trie = ["cargo", "cargo test", "git status"]
+            .iter()
+            .collect::<Trie>();
+
+        let mut words = Vec::new();
+        for word in &trie {
+            words.push(word);
+        }
+        assert_eq!(words, trie.words().collect::<Vec<_>>());
+        assert_eq!(words.len(), 3);
+    }
+
+    #[test]
+    fn fuzzy_words() {
+        let trie = ["Hello world", "Hello sir", "cat", "cot", "cut", "dog"]
+            .iter()
+            .collect::<Trie>();
+
+        assert_eq!(trie.fuzzy_words("Hello world", 0), vec!["Hello world"]);
+        assert_eq!(trie.fuzzy_words("Helo world", 1), vec!["Hello world"]);
+        assert_eq!(trie.fuzzy_words("Hello sr", 1), vec!["Hello sir"]);
+        assert_eq!(trie.fuzzy_words("cat", 1), vec!["cat", "cot", "cut"]);
+        assert!(trie.fuzzy_words("Helo", 0).is_empty());
+
+        assert!(trie.fuzzy_words("xxt", 1).is_empty());
+        assert_eq!(trie.fuzzy_words("xxt", 2), vec!["cat", "cot", "cut"]);
+        assert_eq!(trie.fuzzy_words("cxg", 2), vec!["cat", "cot", "cut", "dog"]);
+    }
+
+    #[test]
+    fn words_containing() {
+        let trie = Trie::from_words(["cargo build", "cargo test", "git status", "ls"]);
+
+        assert_eq!(trie.words_containing("").len(), 4);
+        assert_eq!(trie.words_containing("t"), vec!["cargo test", "git status"]);
+        assert_eq!(trie.words_containing("go b"), vec!["cargo build"]);
+        assert_eq!(trie.words_containing("status"), vec!["git status"]);
+        assert!(trie.words_containing("push").is_empty());
+        assert!(trie.words_containing("Cargo").is_empty());
+
+        let mut trie = Trie::new_case_insensitive();
+        trie.insert("Cargo Build", ());
+        assert_eq!(trie.words_containing("build"), vec!["Cargo Build"]);
+    }
+
+    #[test]
+    fn words_matching() {
+        let trie = Trie::from_words(["hello", "hallo", "hullo", "help", "hero", "ho", "halo"]);
+
+        assert_eq!(
+            trie.words_matching("h?llo"),
+            vec!["hallo", "hello", "hullo"]
+        );
+        assert_eq!(
+            trie.words_matching("h*o"),
+            vec!["hallo", "halo", "hello", "hero", "ho", "hullo"]
+        );
+        assert_eq!(trie.words_matching("he*"), vec!["hello", "help", "hero"]);
+        assert_eq!(
+            trie.words_matching("*l*l*"),
+            vec!["hallo", "hello", "hullo"]
+        );
+        assert_eq!(trie.words_matching("help"), vec!["help"]);
+        assert!(trie.words_matching("h?lp?").is_empty());
+        assert!(trie.words_matching("x*").is_empty());
+        assert!(trie.words_matching("").is_empty());
+
+        assert_eq!(trie.words_matching("*"), trie.words().collect::<Vec<_>>());
+        assert_eq!(trie.words_matching("**"), trie.words().collect::<Vec<_>>());
+        assert_eq!(trie.words_matching("??"), vec!["ho"]);
+        assert_eq!(trie.words_matching("????"), vec!["halo", "help", "hero"]);
+
+        let mut trie = Trie::new_case_insensitive();
+        trie.insert("Hello", ());
+        assert_eq!(trie.words_matching("h?LLO"), vec!["Hello"]);
+    }
+
+    #[test]
+    fn longest_prefix() {
+        let trie = ["git", "git status", "Hello world", "漢字"]
+            .iter()
+            .collect::<Trie>();
+
+        assert_eq!(
+            trie.longest_prefix("git status --short"),
+            Some("git status")
+        );
+        assert_eq!(trie.longest_prefix("git stash"), Some("git"));
+        assert_eq!(trie.longest_prefix("Hello world"), Some("Hello world"));
+        assert_eq!(trie.longest_prefix("漢字です"), Some("漢字"));
+        assert_eq!(trie.longest_prefix("Hello"), None);
+        assert_eq!(trie.longest_prefix("cargo test"), None);
+        assert_eq!(trie.longest_prefix(""), None);
+    }
+
+    #[test]
+    fn longest_common_prefix() {
+        let mut trie = Trie::new();
+        assert_eq!(trie.longest_common_prefix(), "");
+
+        trie.insert("flower", ());
+        assert_eq!(trie.longest_common_prefix(), "flower");
+
+        trie.insert("flow", ());
+        trie.insert("flight", ());
+        assert_eq!(trie.longest_common_prefix(), "fl");
+        assert_eq!(trie.longest_common_prefix_with("flo"), "flow");
+        assert_eq!(trie.longest_common_prefix_with("flowe"), "flower");
+        assert_eq!(trie.longest_common_prefix_with("fli"), "flight");
+        assert_eq!(trie.longest_common_prefix_with("flx"), "");
+
+        trie.insert("漢字", ());
+        assert_eq!(trie.longest_common_prefix(), "");
+        assert_eq!(trie.longest_common_prefix_with("漢"), "漢字");
+
+        let mut trie = Trie::new_case_insensitive();
+        trie.insert("Cargo Build", ());
+        trie.insert("cargo bench", ());
+        assert_eq!(trie.longest_common_prefix_with("CARGO"), "cargo b");
+    }
+
+    #[test]
+    fn longest_common_prefix_skips_nodes_without_words() {
+        let mut trie = Trie::new();
+        trie.insert("abc", ());
+        trie.delete_after_prefix("ab", "c");
+        assert_eq!(trie.longest_common_prefix_with("a"), "");
+
+        trie.insert("ad", ());
+        assert_eq!(trie.longest_common_prefix_with("a"), "ad");
+        assert_eq!(trie.longest_common_prefix(), "ad");
+        assert_eq!(trie.longest_common_prefix_with("ab"), "");
+    }
+
+    #[test]
+    fn case_insensitive() {
+        let mut trie = Trie::new_case_insensitive();
+        trie.insert("Hello world", ());
+        trie.insert("Hello", ());
+
+        assert!(trie.contains("HELLO"));
+        assert!(trie.contains("hello World"));
+        assert_eq!(
+            trie.words_with_prefix("hello").collect::<Vec<_>>(),
+            vec!["Hello", "Hello world"]
+        );
+        assert_eq!(trie.longest_prefix("HELLO WORLD!"), Some("HELLO WORLD"));
+        assert_eq!(trie.fuzzy_words("helo", 1), vec!["Hello"]);
+        assert_eq!(
+            trie.filter_present(&["hello", "Goedemorgen"]),
+            vec!["hello"]
+        );
+
+        // Inserting another casing replaces the word, rather than adding it
+        trie.insert("HELLO", ());
+        assert_eq!(trie.len(), 2);
+        assert_eq!(
+            trie.words().collect::<Vec<_>>(),
+            vec!["HELLO", "Hello world"]
+        );
+
+        trie.delete("hello WORLD");
+        assert_eq!(trie.words().collect::<Vec<_>>(), vec!["HELLO"]);
+    }
+
+    #[test]
+    fn case_sensitive_by_default() {
+        let mut trie = Trie::new();
+        trie.insert("Hello", ());
+
+        assert!(trie.contains("Hello"));
+        assert!(!trie.contains("HELLO"));
+        assert_eq!(trie.words_with_prefix("h").next(), None);
+    }
+
+    #[test]
+    fn remove_prefix() {
+        let mut trie = [
+            "tmp",
+            "tmp_a",
+            "tmp_b",
+            "tmp_b/c",
+            "tm",
+            "tmpfs",
+            "git status",
+        ]
+        .iter()
+        .collect::<Trie>();
+
+        assert_eq!(trie.remove_prefix("tmp_"), 3);
+        assert_eq!(trie.len(), 4);
+        assert_eq!(
+            trie.words().collect::<Vec<_>>(),
+            vec!["git status", "tm", "tmp", "tmpfs"]
+        );
+        assert!(trie.find("tmp_").is_none());
+
+        assert_eq!(trie.remove_prefix("tmp_"), 0);
+        assert_eq!(trie.remove_prefix("cargo"), 0);
+
+        // The prefix itself is removed too, but its ancestors are kept
+        assert_eq!(trie.remove_prefix("tmp"), 2);
+        assert_eq!(trie.words().collect::<Vec<_>>(), vec!["git status", "tm"]);
+
+        assert_eq!(trie.remove_prefix(""), 2);
+        assert!(trie.is_empty());
+    }
+
+    #[test]
+    fn merge() {
+        let mut trie = Trie::new();
+        trie.insert("cargo", 1);
+        trie.insert("cargo test", 2);
+        trie.insert("git status", 3);
+
+        let mut other = Trie::new();
+        other.insert("cargo test", 20);
+        other.insert("cargo build", 4);
+        other.insert("ls", 5);
+
+        trie.merge(other);
+        assert_eq!(trie.len(), 5);
+        assert_eq!(
+            trie.words().collect::<Vec<_>>(),
+            vec!["cargo", "cargo build", "cargo test", "git status", "ls"]
+        );
+        assert_eq!(trie.get("cargo test"), Some(&20));
+        assert_eq!(trie.find("cargo test").unwrap().frequency(), 2);
+        assert_eq!(
+            trie.words_in_insertion_order().collect::<Vec<_>>(),
+            vec!["cargo", "git status", "cargo test", "cargo build", "ls"]
+        );
+
+        let mut union = ["cargo", "git status"].iter().collect::<Trie>();
+        union += ["cargo", "cargo test"].iter().collect::<Trie>();
+        assert_eq!(
+            union.words().collect::<Vec<_>>(),
+            ["cargo", "cargo test", "git status"]
+                .iter()
+                .collect::<Trie>()
+                .words()
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn top_completions() {
+        let mut trie = Trie::new();
+        for (word, count) in &[
+            ("git", 5),
+            ("git status", 3),
+            ("git push", 1),
+            ("git log", 3),
+            ("git commit", 4),
+            ("ls", 9),
+        ] {
+            for _ in 0..*count {
+                trie.insert(word, ());
+            }
+        }
+
+        assert_eq!(
+            trie.top_completions("git", 10),
+            vec!["git commit", "git log", "git status", "git push"]
+        );
+        assert_eq!(
+            trie.top_completions("git", 2),
+            vec!["git commit", "git log"]
+        );
+        assert_eq!(trie.top_completions("", 2), vec!["ls", "git"]);
+        assert!(trie.top_completions("git", 0).is_empty());
+        assert!(trie.top_completions("cargo", 3).is_empty());
+
+        // Deleting a word resets its frequency
+        trie.delete("git commit");
+        trie.insert("git commit", ());
+        assert_eq!(trie.find("git commit").unwrap().frequency(), 1);
+        assert_eq!(trie.top_completions("git ", 1), vec!["git log"]);
+    }
+
+    #[test]
+    fn filter_present() {
+        let mut trie = Trie::new();
+        trie.insert("git status", ());
+        trie.insert("git push", ());
+        trie.insert("ls", ());
+
+        let candidates = [
+            "ls",
+            "cargo build",
+            "git push",
+            "git",
+            "",
+            "git status --short",
+            "ls",
+            "zsh",
+        ];
+        let present = trie.filter_present(&candidates);
+        assert_eq!(present, vec!["ls", "git push", "ls"]);
+
+        let naive = candidates
+            .iter()
+            .copied()
+            .filter(|candidate| trie.words().any(|word| word == candidate))
+            .collect::<Vec<_>>();
+        assert_eq!(present, naive);
+    }
+
+    #[test]
+    fn normalizer_trims() {
+        let mut trie = Trie::with_normalizer(|word| Some(word.trim().to_string()));
+
+        assert!(trie.insert("Hello world!\n", ()));
+        assert!(trie.find("Hello world!").is_some());
+        assert!(trie.find("Hello world!\n").is_none());
+    }
+
+    #[test]
+    fn normalizer_rejects() {
+        let mut trie = Trie::with_normalizer(|word| {
+            if word.chars().count() > 5 {
+                None
+            } else {
+                Some(word.to_string())
+            }
+        });
+
+        assert!(trie.insert("Hello", ()));
+        assert!(!trie.insert("Hello world!", ()));
+        assert!(trie.find("Hello").is_some());
+        assert!(trie.find("Hello ").is_none());
+        assert_eq!(trie.len(), 1);
+    }
+
+    #[test]
+    fn prefix_count() {
+        let mut trie = Trie::new();
+        assert_eq!(trie.prefix_count(), 0);
+
+        // Proper prefixes: "a", "ab", "b"
+        trie.insert("abc", ());
+        trie.insert("abd", ());
+        trie.insert("ba", ());
+        trie.insert("c", ());
+        assert_eq!(trie.prefix_count(), 3);
+    }
+
+    #[test]
+    fn height() {
+        let mut trie = Trie::new();
+        assert_eq!(trie.height(), 0);
+
+        trie.insert("hello", ());
+        assert_eq!(trie.height(), 5);
+
+        trie.insert("hi", ());
+        trie.insert("goedemorgen", ());
+        trie.insert("漢字", ());
+        assert_eq!(trie.height(), 11);
+
+        trie.delete("goedemorgen");
+        assert_eq!(trie.height(), 5);
+
+        // The nodes of the prefix are kept, but aren't part of any word
+        let mut trie = Trie::new();
+        trie.insert("abc", ());
+        trie.delete_after_prefix("ab", "c");
+        assert_eq!(trie.height(), 0);
+
+        trie.insert("a", ());
+        assert_eq!(trie.height(), 1);
+    }
+
+    #[test]
+    fn node_count() {
+        let mut trie = Trie::new();
+        assert_eq!(trie.node_count(), 0);
+
+        // "a", "ab" and "ac"
+        trie.insert("ab", ());
+        trie.insert("ac", ());
+        assert_eq!(trie.node_count(), 3);
+        assert_eq!(trie.node_count(), trie.nodes().count());
+
+        trie.insert("a", ());
+        trie.insert("abcd", ());
+        assert_eq!(trie.node_count(), 5);
+    }
+
+    #[test]
+    fn memory_estimate() {
+        let mut trie = Trie::new();
+        let empty = trie.memory_estimate();
+        assert_eq!(empty, mem::size_of::<Trie>());
+
+        trie.insert("ab", ());
+        trie.insert("ac", ());
+        let node = mem::size_of::<(char, Node)>();
+        assert_eq!(trie.memory_estimate(), empty + 3 * node + 4);
+    }
+
+    #[test]
+    fn delete_releases_memory() {
+        let words = (0..1000)
+            .map(|i| format!("cargo test -- test_{}", i))
+            .collect::<Vec<_>>();
+        let mut trie = Trie::from_words(&words);
+        let full = trie.memory_estimate();
+
+        for word in words.iter().filter(|word| !word.ends_with('7')) {
+            assert!(trie.delete(word));
+        }
+
+        // Deleting removes the nodes which are no longer part of any word, and the words they
+        // stored, so the trie takes as much memory as one holding only the remaining words
+        let remaining = Trie::from_words(words.iter().filter(|word| word.ends_with('7')));
+        assert_eq!(trie.len(), 100);
+        assert_eq!(trie, remaining);
+        assert_eq!(trie.node_count(), remaining.node_count());
+        assert_eq!(trie.memory_estimate(), remaining.memory_estimate());
+        assert!(trie.memory_estimate() * 5 < full);
+    }
+
+    #[test]
+    fn nodes() {
+        let mut trie = Trie::new();
+        trie.insert("ab", ());
+        trie.insert("ac", ());
+
+        let nodes = trie.nodes().collect::<Vec<_>>();
+        assert_eq!(
+            nodes,
+            vec![
+                ("a".to_string(), false),
+                ("ab".to_string(), true),
+                ("ac".to_string(), true)
+            ]
+        );
+    }
+
+    #[test]
+    fn only_word_nodes_store_words() {
+        let word = "a".repeat(1000);
+        let mut trie = Trie::new();
+        trie.insert(&word, ());
+        trie.insert(&word[..500], ());
+
+        // Storing the path in every node would take 1000 * 1001 / 2 bytes for the long word alone
+        let mut stored = 0;
+        let mut stack = vec![&trie.root];
+        while let Some(head) = stack.pop() {
+            stack.extend(head.children.values());
+            stored += head.value.capacity();
+        }
+        assert_eq!(stored, 1500);
+        assert_eq!(
+            trie.words().collect::<Vec<_>>(),
+            vec![&word[..500], &word[..]]
+        );
+
+        trie.delete(&word[..500]);
+        assert_eq!(trie.find(&word[..500]).unwrap().value, "");
+    }
+
+    #[test]
+    fn words_sorted() {
+        let trie = [
+            "git status",
+            "cargo test",
+            "Goedemorgen",
+            "cargo",
+            "cargo build",
+            "git",
+            "ls",
+        ]
+        .iter()
+        .collect::<Trie>();
+
+        assert_eq!(
+            trie.words().collect::<Vec<_>>(),
+            vec![
+                "Goedemorgen",
+                "cargo",
+                "cargo build",
+                "cargo test",
+                "git",
+                "git status",
+                "ls"
+            ]
+        );
+        assert_eq!(
+            trie.words_with_prefix("cargo").collect::<Vec<_>>(),
+            vec!["cargo", "cargo build", "cargo test"]
+        );
+        assert_eq!(
+            trie.words_bfs().collect::<Vec<_>>(),
+            vec![
+                "ls",
+                "git",
+                "cargo",
+                "cargo test",
+                "git status",
+                "Goedemorgen",
+                "cargo build"
+            ]
+        );
+    }
+
+    #[test]
+    fn words_bfs() {
+        let mut trie = Trie::new();
+        trie.insert("abc", ());
+        trie.insert("b", ());
+        trie.insert("cd", ());
+        trie.insert("cef", ());
+
+        let words = trie.words_bfs().collect::<Vec<_>>();
+        assert_eq!(words.len(), 4);
+        assert_eq!(words[0], "b");
+        assert_eq!(words[1], "cd");
+        assert!(words[2..].contains(&&"abc".to_string()));
+        assert!(words[2..].contains(&&"cef".to_string()));
+    }
+
+    #[test]
+    fn words_in_insertion_order() {
+        let mut trie = Trie::new();
+        trie.insert("cargo test", ());
+        trie.insert("git status", ());
+        trie.insert("cargo build", ());
+        trie.insert("ls", ());
+
+        assert_eq!(
+            trie.words_in_insertion_order().collect::<Vec<_>>(),
+            vec!["cargo test", "git status", "cargo build", "ls"]
+        );
+
+        // Re-inserting a word moves it to the end
+        trie.insert("git status", ());
+        trie.delete("ls");
+        assert_eq!(
+            trie.words_in_insertion_order().collect::<Vec<_>>(),
+            vec!["cargo test", "cargo build", "git status"]
+        );
+    }
+
+    #[test]
+    fn get_or_insert_with() {
+        let mut trie = Trie::new();
+
+        *trie.get_or_insert_with("cargo", || 1) += 1;
+        assert_eq!(trie.get("cargo"), Some(&2));
+        assert_eq!(trie.len(), 1);
+        assert_eq!(trie.find("cargo").unwrap().frequency(), 1);
+
+        // The closure isn't called for a word which is already present
+        *trie.get_or_insert_with("cargo", || panic!("cargo is present")) += 1;
+        assert_eq!(trie.get("cargo"), Some(&3));
+        assert_eq!(trie.len(), 1);
+        assert_eq!(trie.find("cargo").unwrap().frequency(), 1);
+
+        // A prefix of another word is no word itself until it is inserted
+        *trie.get_or_insert_with("car", || 10) += 1;
+        assert_eq!(trie.get("car"), Some(&11));
+        assert_eq!(trie.len(), 2);
+
+        let mut trie = Trie::new_case_insensitive();
+        trie.insert("Cargo", 1);
+        assert_eq!(*trie.get_or_insert_with("cARGO", || 0), 1);
+        assert_eq!(trie.len(), 1);
+    }
+
+    #[test]
+    fn lru_capacity() {
+        let mut trie = Trie::with_lru_capacity(3);
+        trie.insert("cargo test", ());
+        trie.insert("git status", ());
+        trie.insert("cargo build", ());
+        assert_eq!(trie.len(), 3);
+
+        // Exceeding the capacity evicts the least recently inserted word
+        trie.insert("ls", ());
+        assert_eq!(trie.len(), 3);
+        assert!(trie.find("cargo test").is_none());
+        assert!(trie.find("cargo build").is_some());
+
+        // Re-inserting a word marks it as recently used
+        trie.insert("git status", ());
+        trie.insert("git push", ());
+        assert_eq!(trie.len(), 3);
+        assert!(trie.find("cargo build").is_none());
+        assert!(trie.find("git status").is_some());
+        assert!(trie.find("ls").is_some());
+        assert!(trie.find("git push").is_some());
+    }
+
+    #[test]
+    fn lru_capacity_shared_prefix() {
+        let mut trie = Trie::with_lru_capacity(2);
+        trie.insert("cargo", ());
+        trie.insert("cargo build", ());
+
+        // Evicting a word which is a prefix of a surviving word keeps the nodes of the survivor
+        trie.insert("car", ());
+        assert_eq!(trie.len(), 2);
+        assert!(!trie.contains("cargo"));
+        assert!(trie.contains("cargo build"));
+        assert!(trie.contains("car"));
+        assert_eq!(
+            trie.words_with_prefix("car").collect::<Vec<_>>(),
+            vec!["car", "cargo build"]
+        );
+
+        // Evicting a word which extends a surviving word removes only its own branch
+        trie.insert("cat", ());
+        assert_eq!(trie.words().collect::<Vec<_>>(), vec!["car", "cat"]);
+        assert!(trie.find("carg").is_none());
+        assert_eq!(trie.node_count(), 4);
+    }
+
+    #[test]
+    fn lru_capacity_recency_index() {
+        let mut trie = Trie::with_lru_capacity(3);
+        for word in &["cargo test", "cargo build", "git status", "git push"] {
+            trie.insert(word, ());
+        }
+        assert_eq!(trie.recency.len(), 3);
+
+        // Words deleted by prefix leave stale entries, which are skipped when evicting
+        trie.remove_prefix("git");
+        trie.insert("ls", ());
+        trie.insert("cd", ());
+        trie.insert("cargo test", ());
+        trie.insert("pwd", ());
+        assert_eq!(
+            trie.words().collect::<Vec<_>>(),
+            vec!["cargo test", "cd", "pwd"]
+        );
+
+        // The stale entries don't pile up in the index
+        let mut trie = Trie::with_lru_capacity(10);
+        trie.insert("ls", ());
+        for i in 0..1000 {
+            trie.insert(&format!("echo {}", i), ());
+            trie.remove_prefix("echo");
+        }
+        assert!(trie.recency.len() < 32);
+        assert_eq!(trie.words().collect::<Vec<_>>(), vec!["ls"]);
+    }
+
+    #[test]
+    fn expire() {
+        let now = Arc::new(Mutex::new(SystemTime::UNIX_EPOCH));
+        let clock = Arc::clone(&now);
+        let mut trie = Trie::with_clock(move || *clock.lock().unwrap());
+
+        let advance = |secs| *now.lock().unwrap() += Duration::from_secs(secs);
+
+        trie.insert("cargo build", ());
+        trie.insert("cargo test", ());
+        advance(60);
+        trie.insert("git status", ());
+        advance(30);
+        trie.insert("cargo build", ());
+
+        let current = *now.lock().unwrap();
+        assert_eq!(trie.expire(Duration::from_secs(45), current), 1);
+        assert!(trie.find("cargo test").is_none());
+        assert!(trie.find("cargo build").is_some());
+        assert!(trie.find("git status").is_some());
+
+        advance(30);
+        let current = *now.lock().unwrap();
+        assert_eq!(trie.expire(Duration::from_secs(45), current), 1);
+        assert!(trie.find("git status").is_none());
+        assert_eq!(trie.len(), 1);
+    }
+}
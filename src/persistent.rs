@@ -0,0 +1,230 @@
+//! This module provides the [`PersistentTrie`], a trie whose snapshots share their nodes.
+
+use std::{collections::HashMap, sync::Arc};
+
+/// A trie which can be snapshotted in constant time.
+///
+/// Unlike the [`Trie`](crate::Trie), the nodes of a `PersistentTrie` are reference counted, and
+/// shared between a trie and its snapshots. Modifying the trie copies only the nodes on the path to
+/// the inserted or deleted word, which are shared with a snapshot, all other nodes stay shared.
+/// A snapshot is never affected by modifications of the trie it was taken from, and the other way
+/// around.
+///
+/// Like in the [`Trie`](crate::Trie), every node records whether a word ends at it, so a word
+/// which is a prefix of another word is a word of its own.
+///
+/// # Examples
+///
+/// ```
+/// use treeline::PersistentTrie;
+///
+/// let mut history = PersistentTrie::new();
+/// history.insert("git status");
+///
+/// // Speculatively insert a word, and roll back to the snapshot
+/// let snapshot = history.snapshot();
+/// history.insert("git push");
+/// assert!(history.contains("git push"));
+///
+/// history = snapshot;
+/// assert!(!history.contains("git push"));
+/// assert!(history.contains("git status"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PersistentTrie {
+    /// The root node, which doesn't correspond to a character.
+    root: Arc<Node>,
+}
+
+/// A node in a [`PersistentTrie`].
+#[derive(Debug, Clone, Default)]
+struct Node {
+    /// The nodes following this node, indexed by their character.
+    children: HashMap<char, Arc<Node>>,
+    /// Whether the path from the root to this node is a complete word.
+    is_word: bool,
+}
+
+impl PersistentTrie {
+    /// Create an empty persistent trie.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a snapshot of the trie, which shares all its nodes with the trie.
+    ///
+    /// This is as cheap as cloning an [`Arc`], and a trie can be rolled back to the snapshot by
+    /// assigning the snapshot to it.
+    pub fn snapshot(&self) -> Self {
+        self.clone()
+    }
+
+    /// Inserts the `word` into the trie, copying the nodes on its path which are shared with a
+    /// snapshot.
+    pub fn insert(&mut self, word: &str) {
+        let mut node = &mut self.root;
+        for c in word.chars() {
+            node = Arc::make_mut(node).children.entry(c).or_default();
+        }
+
+        if !node.is_word {
+            Arc::make_mut(node).is_word = true;
+        }
+    }
+
+    /// Deletes the `word` from the trie, returns whether it was present.
+    ///
+    /// Nothing is copied if the `word` isn't present.
+    pub fn delete(&mut self, word: &str) -> bool {
+        if !self.contains(word) {
+            return false;
+        }
+
+        Node::delete(&mut self.root, &word.chars().collect::<Vec<_>>());
+        true
+    }
+
+    /// Returns whether the `word` is present in the trie.
+    pub fn contains(&self, word: &str) -> bool {
+        let mut node = &self.root;
+        for c in word.chars() {
+            match node.children.get(&c) {
+                Some(child) => node = child,
+                None => return false,
+            }
+        }
+
+        node.is_word
+    }
+
+    /// Returns an iterator over all the words in the trie, in no particular order.
+    pub fn words(&self) -> impl Iterator<Item = String> {
+        let mut words = Vec::new();
+        let mut stack = vec![(String::new(), &self.root)];
+        while let Some((prefix, node)) = stack.pop() {
+            for (c, child) in &node.children {
+                let mut word = prefix.clone();
+                word.push(*c);
+                stack.push((word, child));
+            }
+
+            if node.is_word {
+                words.push(prefix);
+            }
+        }
+
+        words.into_iter()
+    }
+}
+
+impl Node {
+    /// Deletes the word spelled by `chars` below `node`, removing the nodes which no longer lead
+    /// to a word.
+    fn delete(node: &mut Arc<Node>, chars: &[char]) {
+        let node = Arc::make_mut(node);
+        let (c, rest) = match chars.split_first() {
+            Some(split) => split,
+            None => {
+                node.is_word = false;
+                return;
+            }
+        };
+
+        if let Some(child) = node.children.get_mut(c) {
+            Self::delete(child, rest);
+            if !child.is_word && child.children.is_empty() {
+                node.children.remove(c);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PersistentTrie;
+    use std::sync::Arc;
+
+    fn sorted(trie: &PersistentTrie) -> Vec<String> {
+        let mut words = trie.words().collect::<Vec<_>>();
+        words.sort_unstable();
+        words
+    }
+
+    #[test]
+    fn insert_and_delete() {
+        let mut trie = PersistentTrie::new();
+        trie.insert("git status");
+        trie.insert("git push");
+        trie.insert("ls");
+
+        assert_eq!(sorted(&trie), vec!["git push", "git status", "ls"]);
+        assert!(trie.contains("ls"));
+        assert!(!trie.contains("git"));
+
+        assert!(trie.delete("git push"));
+        assert!(!trie.delete("git push"));
+        assert!(!trie.delete("git"));
+        assert_eq!(sorted(&trie), vec!["git status", "ls"]);
+    }
+
+    #[test]
+    fn word_which_is_a_prefix() {
+        let mut trie = PersistentTrie::new();
+        trie.insert("git");
+        trie.insert("git status");
+
+        assert!(trie.contains("git"));
+        assert!(trie.contains("git status"));
+        assert!(!trie.contains("git "));
+        assert_eq!(sorted(&trie), vec!["git", "git status"]);
+
+        // Deleting either word keeps the other one
+        let snapshot = trie.snapshot();
+        assert!(trie.delete("git"));
+        assert!(!trie.contains("git"));
+        assert_eq!(sorted(&trie), vec!["git status"]);
+
+        trie = snapshot;
+        assert!(trie.delete("git status"));
+        assert_eq!(sorted(&trie), vec!["git"]);
+        assert!(trie.root.children[&'g'].children[&'i'].children[&'t']
+            .children
+            .is_empty());
+    }
+
+    #[test]
+    fn snapshot_is_unaffected() {
+        let mut trie = PersistentTrie::new();
+        trie.insert("git status");
+        trie.insert("ls");
+
+        let snapshot = trie.snapshot();
+        trie.insert("git push");
+        trie.delete("ls");
+
+        assert_eq!(sorted(&trie), vec!["git push", "git status"]);
+        assert_eq!(sorted(&snapshot), vec!["git status", "ls"]);
+    }
+
+    #[test]
+    fn snapshot_shares_nodes() {
+        let mut trie = PersistentTrie::new();
+        trie.insert("git status");
+        trie.insert("ls");
+
+        let snapshot = trie.snapshot();
+        assert!(Arc::ptr_eq(&trie.root, &snapshot.root));
+
+        // Only the path to the new word is copied, the nodes of "ls" are still shared
+        trie.insert("git push");
+        assert!(!Arc::ptr_eq(&trie.root, &snapshot.root));
+        assert!(!Arc::ptr_eq(
+            &trie.root.children[&'g'],
+            &snapshot.root.children[&'g']
+        ));
+        assert!(Arc::ptr_eq(
+            &trie.root.children[&'l'],
+            &snapshot.root.children[&'l']
+        ));
+    }
+}
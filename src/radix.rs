@@ -0,0 +1,279 @@
+//! This module provides the [`RadixTrie`], a trie whose chains of single children are compressed.
+
+use std::{collections::BTreeMap, mem};
+
+/// A trie which stores a chain of nodes with a single child as one node.
+///
+/// Unlike the [`Trie`](crate::Trie), which stores a node for every character, every node of a
+/// `RadixTrie` holds a segment of characters, and only branches where words diverge. This saves a
+/// lot of memory for long words with few branches, like the lines in the history of a REPL. Like
+/// in the [`Trie`](crate::Trie), the words are yielded in lexicographic order.
+///
+/// # Examples
+///
+/// ```
+/// use treeline::RadixTrie;
+///
+/// let mut history = RadixTrie::new();
+/// history.insert("cargo build --release");
+/// history.insert("cargo test --release");
+///
+/// assert!(history.contains("cargo test --release"));
+/// assert!(history.find("cargo"));
+/// assert_eq!(
+///     history.words_with_prefix("cargo").collect::<Vec<_>>(),
+///     vec!["cargo build --release", "cargo test --release"]
+/// );
+///
+/// // The nodes "cargo ", "build --release" and "test --release"
+/// assert_eq!(history.node_count(), 3);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RadixTrie {
+    /// The root node, whose segment is always empty.
+    root: Node,
+    /// The number of words in the trie.
+    word_count: usize,
+}
+
+/// A node in a [`RadixTrie`].
+#[derive(Debug, Clone, Default)]
+struct Node {
+    /// The characters between the parent node and this node.
+    segment: String,
+    /// The nodes following this node, indexed by the first character of their segment.
+    children: BTreeMap<char, Node>,
+    /// Whether the path from the root to this node is a complete word.
+    is_word: bool,
+}
+
+impl RadixTrie {
+    /// Create an empty radix trie.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts the `word` into the trie.
+    ///
+    /// A node whose segment only partially matches the `word` is split where they diverge.
+    pub fn insert(&mut self, word: &str) {
+        if self.root.insert(word) {
+            self.word_count += 1;
+        }
+    }
+
+    /// Returns whether the `word` was inserted into the trie as a complete word.
+    pub fn contains(&self, word: &str) -> bool {
+        match self.root.locate(word) {
+            Some((node, 0)) => node.is_word,
+            _ => false,
+        }
+    }
+
+    /// Returns whether the `prefix` is a prefix of the words in the trie, like
+    /// [`Trie::find`](crate::Trie::find) returning a node.
+    pub fn find(&self, prefix: &str) -> bool {
+        self.root.locate(prefix).is_some()
+    }
+
+    /// Returns the number of words in the trie.
+    pub fn len(&self) -> usize {
+        self.word_count
+    }
+
+    /// Returns whether the trie contains no words.
+    pub fn is_empty(&self) -> bool {
+        self.word_count == 0
+    }
+
+    /// Returns the number of nodes in the trie, excluding the root.
+    pub fn node_count(&self) -> usize {
+        self.root.node_count() - 1
+    }
+
+    /// Returns an iterator over all the words in the trie, in lexicographic order.
+    pub fn words(&self) -> impl Iterator<Item = String> {
+        let mut words = Vec::new();
+        self.root.collect_words(String::new(), &mut words);
+        words.into_iter()
+    }
+
+    /// Returns an iterator over the words in the trie with the given prefix, in lexicographic
+    /// order.
+    ///
+    /// Like [`Trie::words_with_prefix`](crate::Trie::words_with_prefix), the `prefix` itself is
+    /// yielded first if it is a word.
+    pub fn words_with_prefix(&self, prefix: &str) -> impl Iterator<Item = String> {
+        let mut words = Vec::new();
+        if let Some((node, remaining)) = self.root.locate(prefix) {
+            // The `prefix` may end halfway through the segment of `node`, in which case the path
+            // to `node` continues with the `remaining` part of its segment
+            let path = format!(
+                "{}{}",
+                prefix,
+                &node.segment[node.segment.len() - remaining..]
+            );
+            node.collect_words(path, &mut words);
+        }
+
+        words.into_iter()
+    }
+}
+
+impl Node {
+    /// Creates a new `Node` holding the given segment, which ends a word.
+    fn leaf(segment: &str) -> Self {
+        Self {
+            segment: segment.to_string(),
+            children: BTreeMap::new(),
+            is_word: true,
+        }
+    }
+
+    /// Returns the first character of the segment, which is the key of the node in its parent.
+    fn first(&self) -> char {
+        self.segment.chars().next().unwrap_or_default()
+    }
+
+    /// Inserts the `word` under the current node, returns whether it wasn't present yet.
+    fn insert(&mut self, word: &str) -> bool {
+        let first = match word.chars().next() {
+            Some(first) => first,
+            None => return !mem::replace(&mut self.is_word, true),
+        };
+
+        let child = match self.children.get_mut(&first) {
+            Some(child) => child,
+            None => {
+                self.children.insert(first, Self::leaf(word));
+                return true;
+            }
+        };
+
+        // If the `word` diverges from the segment of `child`, split `child` so the common part is
+        // its own node, of which the rest of the original segment is a child
+        let common = common_prefix_len(&child.segment, word);
+        if common < child.segment.len() {
+            let tail = Self {
+                segment: child.segment.split_off(common),
+                children: mem::take(&mut child.children),
+                is_word: mem::replace(&mut child.is_word, false),
+            };
+            child.children.insert(tail.first(), tail);
+        }
+
+        child.insert(&word[common..])
+    }
+
+    /// Returns the node at which the `text` ends, together with the number of bytes of its
+    /// segment following the `text`, or [`None`] if no word starts with the `text`.
+    fn locate(&self, text: &str) -> Option<(&Self, usize)> {
+        let first = match text.chars().next() {
+            Some(first) => first,
+            None => return Some((self, 0)),
+        };
+
+        let child = self.children.get(&first)?;
+        if let Some(rest) = text.strip_prefix(child.segment.as_str()) {
+            child.locate(rest)
+        } else if child.segment.starts_with(text) {
+            Some((child, child.segment.len() - text.len()))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the number of nodes under and including the current node.
+    fn node_count(&self) -> usize {
+        1 + self.children.values().map(Node::node_count).sum::<usize>()
+    }
+
+    /// Pushes the words under and including the current node onto `words`, where `path` is the
+    /// path from the root up to and including the current node.
+    fn collect_words(&self, path: String, words: &mut Vec<String>) {
+        if self.is_word {
+            words.push(path.clone());
+        }
+
+        for child in self.children.values() {
+            child.collect_words(format!("{}{}", path, child.segment), words);
+        }
+    }
+}
+
+/// Returns the length in bytes of the longest common prefix of `a` and `b`.
+fn common_prefix_len(a: &str, b: &str) -> usize {
+    a.char_indices()
+        .zip(b.chars())
+        .find(|((_, a), b)| a != b)
+        .map_or_else(|| a.len().min(b.len()), |((i, _), _)| i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RadixTrie;
+    use crate::Trie;
+
+    #[test]
+    fn insert_and_find() {
+        let mut trie = RadixTrie::new();
+        trie.insert("Hello world");
+        trie.insert("Hello");
+        trie.insert("Hello sir");
+        trie.insert("Hello");
+
+        assert_eq!(trie.len(), 3);
+        assert!(trie.contains("Hello"));
+        assert!(!trie.contains("Hello "));
+        assert!(!trie.contains("Hello s"));
+        assert!(trie.find("Hello s"));
+        assert!(!trie.find("Hello x"));
+        assert_eq!(
+            trie.words().collect::<Vec<_>>(),
+            vec!["Hello", "Hello sir", "Hello world"]
+        );
+        assert_eq!(
+            trie.words_with_prefix("Hello").collect::<Vec<_>>(),
+            vec!["Hello", "Hello sir", "Hello world"]
+        );
+        assert_eq!(
+            trie.words_with_prefix("Hello w").collect::<Vec<_>>(),
+            vec!["Hello world"]
+        );
+        assert_eq!(trie.words_with_prefix("Goedemorgen").next(), None);
+    }
+
+    #[test]
+    fn multibyte_characters() {
+        let mut trie = RadixTrie::new();
+        trie.insert("漢字");
+        trie.insert("漢方");
+
+        assert!(trie.contains("漢字"));
+        assert!(trie.find("漢"));
+        assert_eq!(trie.words().collect::<Vec<_>>(), vec!["漢字", "漢方"]);
+    }
+
+    #[test]
+    fn fewer_nodes_than_trie() {
+        let lines = (0..50)
+            .map(|i| format!("cargo test --release -- test_{} --exact --nocapture", i))
+            .chain((0..50).map(|i| format!("git commit --message 'Fix issue {} in the parser'", i)))
+            .collect::<Vec<_>>();
+
+        let mut radix = RadixTrie::new();
+        for line in &lines {
+            radix.insert(line);
+        }
+        let trie = lines.iter().collect::<Trie>();
+
+        assert_eq!(
+            radix.words().collect::<Vec<_>>(),
+            trie.words().cloned().collect::<Vec<_>>()
+        );
+        assert!(radix.node_count() * 10 < trie.node_count());
+        assert!(radix
+            .words_with_prefix("git")
+            .eq(trie.words_with_prefix("git").cloned()));
+    }
+}